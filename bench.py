@@ -9,6 +9,7 @@ Usage:
 import subprocess
 import time
 import csv
+import math
 import sys
 import os
 import signal
@@ -16,6 +17,9 @@ from pathlib import Path
 from dataclasses import dataclass
 from typing import Optional
 
+# Time allowed past the run's duration for startup and a clean quit.
+QUIT_GRACE_SECS = 10
+
 @dataclass
 class FrameStats:
     total_frames: int
@@ -59,23 +63,34 @@ def run_benchmark(duration_secs: float = 5.0, debug: bool = False, build: bool =
     # Run the benchmark
     print(f"Running benchmark for {duration_secs}s ({profile_name})...")
     cmd = ["cargo", "run", "-q", "--features", "fiber"] + profile
+    # Pin the log path; by default it lands in a new per-run output directory.
+    # The app quits on its own after the duration, flushing the buffered log rows that
+    # a signal would lose.
+    env = {
+        **os.environ,
+        "GRID_BENCH_LOG_PATH": str(csv_path),
+        "GRID_BENCH_DURATION_SECS": str(max(1, math.ceil(duration_secs))),
+    }
     proc = subprocess.Popen(
         cmd,
         cwd=grid_dir,
+        env=env,
         stdout=subprocess.DEVNULL,
         stderr=subprocess.DEVNULL,
         preexec_fn=os.setsid
     )
 
-    time.sleep(duration_secs)
-
-    # Gracefully terminate
-    os.killpg(os.getpgid(proc.pid), signal.SIGTERM)
     try:
-        proc.wait(timeout=2)
+        proc.wait(timeout=duration_secs + QUIT_GRACE_SECS)
     except subprocess.TimeoutExpired:
-        os.killpg(os.getpgid(proc.pid), signal.SIGKILL)
-        proc.wait()
+        # Stuck; the rows still buffered are lost.
+        print("Warning: gpui-grid didn't quit on its own, terminating it")
+        os.killpg(os.getpgid(proc.pid), signal.SIGTERM)
+        try:
+            proc.wait(timeout=2)
+        except subprocess.TimeoutExpired:
+            os.killpg(os.getpgid(proc.pid), signal.SIGKILL)
+            proc.wait()
 
     # Check if CSV was created
     if not csv_path.exists():
@@ -1,408 +1,5265 @@
-use std::collections::VecDeque;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::sync::{Mutex, OnceLock};
-use std::time::Instant;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Once, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use gpui::{
-    App, Application, Bounds, Context, ElementId, Entity, Window, WindowBounds, WindowOptions,
-    deferred, div, prelude::*, px, rgb, size,
+    App, Application, AssetSource, Bounds, Context, Corner, ElementId, Entity, FocusHandle,
+    HighlightStyle, KeyDownEvent, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent,
+    Pixels, Point, ScrollHandle, ScrollWheelEvent, SharedString, Size, StyledText, Window,
+    WindowBounds, WindowOptions, canvas, deferred, div, img, prelude::*, px, rgb, size, svg,
 };
 
 #[cfg(feature = "fiber")]
-fn csv_filename() -> &'static str {
-    if cfg!(debug_assertions) {
-        "frame_log_debug.csv"
-    } else {
-        "frame_log_release.csv"
+#[derive(Clone, Copy, PartialEq)]
+enum LogFormat {
+    Csv,
+    JsonLines,
+}
+
+#[cfg(feature = "fiber")]
+impl LogFormat {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_LOG_FORMAT").as_deref() {
+            Some("jsonl") => Self::JsonLines,
+            _ => Self::Csv,
+        }
     }
 }
 
+/// Measurements taken by `FpsView` that accompany each frame's diagnostics.
+#[cfg(feature = "fiber")]
+struct FrameContext {
+    /// Milliseconds since the FPS view was created.
+    elapsed_ms: f64,
+    /// Interval since the previous render, in milliseconds.
+    frame_delta_ms: f64,
+    render_fps: f64,
+    /// CPU time `GridBench::render` spent building the tree.
+    grid_render_time: Duration,
+    /// The grid's rows and columns, which `GRID_BENCH_REPLAY` reads back.
+    rows: usize,
+    cols: usize,
+    viewport: gpui::Size<Pixels>,
+    /// How far the grid is scrolled, in positive pixels.
+    scroll: Point<Pixels>,
+    /// The frame the user marked with the `m` key.
+    marker: bool,
+    /// Whether the frame included the HUD, which `GRID_BENCH_HUD_AB` leaves out of every
+    /// other frame.
+    hud: bool,
+    #[cfg(feature = "rss")]
+    rss_bytes: u64,
+}
+
+/// Named per-frame values shared by the CSV columns and the JSON-lines keys.
+#[cfg(feature = "fiber")]
+fn frame_fields(
+    diag: &gpui::FrameDiagnostics,
+    context: &FrameContext,
+) -> Vec<(&'static str, serde_json::Value)> {
+    use serde_json::json;
+
+    let us = |duration: std::time::Duration| json!(duration.as_micros() as u64);
+    let rounded = |value: f64, scale: f64| json!((value * scale).round() / scale);
+    let pixels = |value: Pixels| rounded(f32::from(value) as f64, 10.0);
+
+    let mut fields = vec![
+        ("frame", json!(diag.frame_number)),
+        ("time_ms", rounded(context.elapsed_ms, 1000.0)),
+        ("delta_ms", rounded(context.frame_delta_ms, 1000.0)),
+        ("layout_fibers", json!(diag.layout_fibers)),
+        ("paint_fibers", json!(diag.paint_fibers)),
+        ("paint_replayed", json!(diag.paint_replayed_subtrees)),
+        ("prepaint_fibers", json!(diag.prepaint_fibers)),
+        ("prepaint_replayed", json!(diag.prepaint_replayed_subtrees)),
+        ("mutated_segments", json!(diag.mutated_pool_segments)),
+        ("total_segments", json!(diag.total_pool_segments)),
+        ("hitboxes", json!(diag.hitboxes_in_snapshot)),
+        ("hitboxes_rebuilt", json!(diag.hitboxes_snapshot_rebuilt)),
+        ("upload_bytes", json!(diag.estimated_instance_upload_bytes)),
+        ("quads", json!(diag.quads)),
+        ("mono_sprites", json!(diag.monochrome_sprites)),
+        ("poly_sprites", json!(diag.polychrome_sprites)),
+        ("reconcile_us", us(diag.reconcile_time)),
+        ("intrinsic_sizing_us", us(diag.intrinsic_sizing_time)),
+        ("layout_us", us(diag.layout_time)),
+        ("prepaint_us", us(diag.prepaint_time)),
+        ("paint_us", us(diag.paint_time)),
+        ("cleanup_us", us(diag.cleanup_time)),
+        ("total_us", us(diag.total_time)),
+        ("grid_render_us", us(context.grid_render_time)),
+        ("render_fps", rounded(context.render_fps, 100.0)),
+        ("rows", json!(context.rows)),
+        ("cols", json!(context.cols)),
+        ("viewport_w", pixels(context.viewport.width)),
+        ("viewport_h", pixels(context.viewport.height)),
+        ("scroll_x", pixels(context.scroll.x)),
+        ("scroll_y", pixels(context.scroll.y)),
+        ("marker", json!(u8::from(context.marker))),
+        ("hud", json!(u8::from(context.hud))),
+    ];
+    #[cfg(feature = "rss")]
+    fields.push(("rss_bytes", json!(context.rss_bytes)));
+    // The same on every row, so logs from different builds can't be mixed up.
+    fields.extend([("backend", json!(BACKEND)), ("profile", json!(PROFILE))]);
+    fields
+}
+
+/// Frame log owned by `FpsView`. Rows are buffered in memory and flushed every
+/// `GRID_BENCH_LOG_FLUSH` frames so per-frame syscalls don't skew the measurements.
+/// With `GRID_BENCH_LOG_MAX_MB`, the file's size is checked at each flush and a log past
+/// it is rotated to `<path>.1`, keeping `GRID_BENCH_LOG_KEEP` older logs.
+#[cfg(feature = "fiber")]
+struct FrameLog {
+    format: LogFormat,
+    out: std::io::BufWriter<Box<dyn Write + Send>>,
+    /// The log file, or `None` when writing to stdout.
+    path: Option<String>,
+    max_bytes: Option<u64>,
+    keep: usize,
+    needs_header: bool,
+    warmup_frames: u64,
+    flush_interval: usize,
+    unflushed_rows: usize,
+    /// The header's columns, which the crash row lines up with.
+    columns: Vec<&'static str>,
+    /// The most recent frame passed to `log`, warmup frames included.
+    last_frame: u64,
+}
+
+/// Every open frame log, for the panic hook to flush. Process-wide, so a panic on any
+/// thread reaches them.
 #[cfg(feature = "fiber")]
-fn log_frame(diag: &gpui::FrameDiagnostics) {
-    static FRAME_LOG: OnceLock<Mutex<std::fs::File>> = OnceLock::new();
+static FRAME_LOGS: std::sync::Mutex<Vec<std::sync::Weak<std::sync::Mutex<FrameLog>>>> =
+    std::sync::Mutex::new(Vec::new());
+
+#[cfg(feature = "fiber")]
+impl FrameLog {
+    fn from_env() -> Self {
+        let format = LogFormat::from_env();
+        // `GRID_BENCH_LOG_PATH=-` writes the log to stdout.
+        let path = output_path(
+            "GRID_BENCH_LOG_PATH",
+            match format {
+                LogFormat::Csv => "frame_log.csv",
+                LogFormat::JsonLines => "frame_log.jsonl",
+            },
+        );
+        let (out, path): (Box<dyn Write + Send>, _) = if path == "-" {
+            (Box::new(std::io::stdout()), None)
+        } else {
+            (
+                Box::new(Self::open(&path).expect("open frame log")),
+                Some(path),
+            )
+        };
+        let max_mb = env_f32("GRID_BENCH_LOG_MAX_MB", 0.0);
+
+        Self {
+            format,
+            out: std::io::BufWriter::with_capacity(LOG_BUFFER_BYTES, out),
+            path,
+            max_bytes: (max_mb > 0.0).then_some((max_mb as f64 * 1024.0 * 1024.0) as u64),
+            keep: env_usize("GRID_BENCH_LOG_KEEP", DEFAULT_LOG_KEEP),
+            needs_header: format == LogFormat::Csv,
+            warmup_frames: env_usize("GRID_BENCH_WARMUP_FRAMES", DEFAULT_WARMUP_FRAMES) as u64,
+            flush_interval: env_usize("GRID_BENCH_LOG_FLUSH", DEFAULT_LOG_FLUSH_FRAMES).max(1),
+            unflushed_rows: 0,
+            columns: Vec::new(),
+            last_frame: 0,
+        }
+    }
+
+    /// Shares the log with the panic hook installed by `install_panic_hook`.
+    fn register(self) -> std::sync::Arc<std::sync::Mutex<Self>> {
+        let log = std::sync::Arc::new(std::sync::Mutex::new(self));
+        let mut logs = lock_unpoisoned(&FRAME_LOGS);
+        logs.retain(|log| log.strong_count() > 0);
+        logs.push(std::sync::Arc::downgrade(&log));
+        log
+    }
+
+    fn log(&mut self, diag: &gpui::FrameDiagnostics, context: &FrameContext) {
+        let fields = frame_fields(diag, context);
+        self.last_frame = diag.frame_number;
+
+        if self.needs_header {
+            self.columns = fields.iter().map(|(name, _)| *name).collect();
+            let _ = writeln!(self.out, "{}", self.columns.join(","));
+            self.needs_header = false;
+        }
+
+        // Shader compilation and first-time allocations make the opening frames unrepresentative.
+        // Markers are kept regardless, since they line the log up with external captures.
+        if diag.frame_number <= self.warmup_frames && !context.marker {
+            return;
+        }
 
-    let log = FRAME_LOG.get_or_init(|| {
-        let filename = csv_filename();
-        let mut file = OpenOptions::new()
+        let line = match self.format {
+            LogFormat::Csv => fields
+                .iter()
+                .map(|(_, value)| value.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            LogFormat::JsonLines => {
+                serde_json::Value::Object(fields.into_iter().map(|(k, v)| (k.into(), v)).collect())
+                    .to_string()
+            }
+        };
+        let _ = writeln!(self.out, "{line}");
+
+        self.unflushed_rows += 1;
+        if self.unflushed_rows >= self.flush_interval || context.marker {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        let _ = self.out.flush();
+        self.unflushed_rows = 0;
+
+        if let (Some(path), Some(max_bytes)) = (&self.path, self.max_bytes)
+            && std::fs::metadata(path).is_ok_and(|metadata| metadata.len() > max_bytes)
+            && let Err(err) = self.rotate()
+        {
+            log::error!("Failed to rotate the frame log, no longer rotating: {err}");
+            self.max_bytes = None;
+        }
+    }
+
+    /// Ends the log with a row for the frame that was in progress when the app panicked,
+    /// with `crash` in the marker column and every other column blank, then flushes it.
+    /// Skips rotation, since the process is about to die.
+    fn log_crash(&mut self) {
+        let frame = self.last_frame + 1;
+        let line = match self.format {
+            // Nothing was logged, so there's no header to line up with.
+            LogFormat::Csv if self.columns.is_empty() => None,
+            LogFormat::Csv => Some(
+                self.columns
+                    .iter()
+                    .map(|&name| match name {
+                        "frame" => frame.to_string(),
+                        "marker" => "crash".to_string(),
+                        _ => String::new(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            LogFormat::JsonLines => {
+                Some(serde_json::json!({ "frame": frame, "marker": "crash" }).to_string())
+            }
+        };
+        if let Some(line) = line {
+            let _ = writeln!(self.out, "{line}");
+        }
+        let _ = self.out.flush();
+    }
+
+    fn open(path: &str) -> std::io::Result<std::fs::File> {
+        OpenOptions::new()
             .create(true)
             .write(true)
             .truncate(true)
-            .open(filename)
-            .expect("open frame log");
+            .open(path)
+    }
+
+    /// Moves the log to `<path>.1`, shifting older logs up a number and deleting the one
+    /// that falls past `keep`, then starts a fresh file.
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let rotated = |n: usize| format!("{path}.{n}");
+
+        if self.keep == 0 {
+            std::fs::remove_file(path)?;
+        } else {
+            if Path::new(&rotated(self.keep)).exists() {
+                std::fs::remove_file(rotated(self.keep))?;
+            }
+            for n in (1..self.keep).rev() {
+                if Path::new(&rotated(n)).exists() {
+                    std::fs::rename(rotated(n), rotated(n + 1))?;
+                }
+            }
+            std::fs::rename(path, rotated(1))?;
+        }
+
+        let out: Box<dyn Write + Send> = Box::new(Self::open(path)?);
+        self.out = std::io::BufWriter::with_capacity(LOG_BUFFER_BYTES, out);
+        self.needs_header = self.format == LogFormat::Csv;
+        Ok(())
+    }
+}
+
+/// Locks `mutex`, taking it over from a panic that hit while it was held.
+#[cfg(feature = "fiber")]
+fn lock_unpoisoned<T>(mutex: &std::sync::Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Chains onto the default panic hook so a crash on any thread leaves every frame log
+/// flushed and ending in a `crash` row before the panic prints as usual. A log that was
+/// mid-write when the panic hit is still locked and can't be reached, so it keeps only
+/// what was flushed. The hook can't tell whether `catch_unwind` will catch the panic, so
+/// a caught panic still writes a `crash` row, and the log carries on after it.
+#[cfg(feature = "fiber")]
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Ok(logs) = FRAME_LOGS.try_lock() {
+            for log in logs.iter().filter_map(std::sync::Weak::upgrade) {
+                if let Ok(mut log) = log.try_lock() {
+                    log.log_crash();
+                }
+            }
+        }
+        default_hook(info);
+    }));
+}
+
+/// Taken just before the application starts, for the time to first frame.
+static LAUNCHED_AT: OnceLock<Instant> = OnceLock::new();
+static FIRST_FRAME: Once = Once::new();
+
+/// This run's output directory, created at startup under `GRID_BENCH_OUTPUT_DIR`.
+static RUN_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Creates `<GRID_BENCH_OUTPUT_DIR or runs>/<UTC timestamp>`, adding a counter if a run
+/// in the same second already took the name.
+fn create_run_dir() -> std::io::Result<PathBuf> {
+    let parent =
+        PathBuf::from(shared_env_var("GRID_BENCH_OUTPUT_DIR").unwrap_or_else(|| "runs".into()));
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let timestamp = utc_timestamp(secs);
+
+    let mut dir = parent.join(&timestamp);
+    let mut attempt = 1;
+    while dir.exists() {
+        attempt += 1;
+        dir = parent.join(format!("{timestamp}_{attempt}"));
+    }
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// `YYYYMMDD-HHMMSS` in UTC, using Howard Hinnant's `civil_from_days` for the date.
+fn utc_timestamp(secs: u64) -> String {
+    let (days, time) = (secs / 86_400, secs % 86_400);
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Writes `config.json` to the run directory: the build, every `GRID_BENCH_*` variable and
+/// command-line flag as set, and each window's settings once defaults are applied.
+fn write_manifest(dir: &Path, windows: Vec<serde_json::Value>) -> std::io::Result<()> {
+    let env = env::vars()
+        .filter(|(name, _)| name.starts_with("GRID_BENCH_"))
+        .map(|(name, value)| (name, serde_json::Value::String(value)))
+        .collect::<serde_json::Map<_, _>>();
+    let manifest = serde_json::json!({
+        "profile": PROFILE,
+        "backend": BACKEND,
+        "features": {
+            "fiber": cfg!(feature = "fiber"),
+            "rss": cfg!(feature = "rss"),
+        },
+        "env": env,
+        "args": CLI_ARGS.get(),
+        "config_file": config_file(),
+        "windows": windows,
+    });
+    let json = serde_json::to_string_pretty(&manifest).map_err(std::io::Error::other)?;
+    std::fs::write(dir.join("config.json"), json + "\n")
+}
+
+thread_local! {
+    /// 1-based index of the window whose views are being built, which selects the
+    /// `GRID_BENCH_<n>_*` overrides read by `env_var`.
+    static WINDOW_INDEX: Cell<usize> = const { Cell::new(1) };
+}
+
+/// `GRID_BENCH_<n>_FOO` for the current window or `GRID_BENCH_FOO`, in the order
+/// `window_env_var` gives.
+fn env_var(name: &str) -> Option<String> {
+    window_env_var(name).map(|(value, _)| value)
+}
+
+/// `GRID_BENCH_FOO` from the command line or the environment, falling back to `foo` in
+/// the config file.
+fn shared_env_var(name: &str) -> Option<String> {
+    setting(name).or_else(|| config_file().get(name).cloned())
+}
+
+/// A setting given as a command-line flag, otherwise from the environment.
+fn setting(name: &str) -> Option<String> {
+    CLI_ARGS
+        .get()
+        .and_then(|args| args.get(name).cloned())
+        .or_else(|| env::var(name).ok())
+}
+
+/// Settings from the command line, keyed by the variable they stand in for:
+/// `--cell-size 32` or `--cell-size=32` acts as `GRID_BENCH_CELL_SIZE=32`, a bare
+/// `--animate` as `GRID_BENCH_ANIMATE=1` and `--no-hover` as `GRID_BENCH_HOVER=0`.
+static CLI_ARGS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+const USAGE: &str = "\
+Usage: gpui-grid [--<setting> <value>]...
+
+Every GRID_BENCH_<SETTING> variable can also be given as a flag, which takes
+precedence over the environment and the GRID_BENCH_CONFIG file:
+
+    --rows <n>             rows in the grid
+    --cols <n>             columns (default: fit the window)
+    --cell-size <px>       cell size
+    --gap <px>             gap between cells
+    --animate [mode]       animate cells (checkerboard: alternate per cell)
+    --hover, --no-hover    hover highlighting
+    --log <path>           frame log path (GRID_BENCH_LOG_PATH)
+    --log-format <fmt>     csv or jsonl
+    --config <path>        TOML config file
+    --windows <n>          number of windows
+    --<n>-<setting> <v>    setting for window <n> only, e.g. --2-rows 500
+    --split <px>,<px>      two grids side by side in one window, with these cell sizes
+    -h, --help             print this help
+";
+
+/// Every setting the app reads, less the `GRID_BENCH_` prefix, so a mistyped flag is an
+/// error instead of a run with the defaults.
+const SETTINGS: &str = "\
+ADAPTIVE ANIMATE BASELINE CALLBACK_HISTORY CELL_CONTENT CELL_DECORATION CELL_H
+CELL_SHAPE CELL_SIZE CELL_SIZE_MODE CELL_W CLICK COLORSPACE COLOR_MODE COLS CONFIG
+DURATION_SECS EMA_ALPHA FOOTER FPS_ALARM FPS_ALARM_PATH FPS_CAP FRAME_BUDGET_MS
+FRAME_HISTORY GAP HEADERS HEIGHT HOVER HOVER_MODE HUD_AB HUE LABEL LAYOUT LOG_FLUSH
+LOG_FORMAT LOG_KEEP LOG_MAX_MB LOG_PATH MINIMAL NESTING ORDER OUTPUT_DIR PATTERN RAMP
+RAMP_FRAMES RAMP_MIN_FPS REFRESH_HZ RENDER_HISTORY REPLAY REPLAY_LOOP ROWS SCRIPT SCROLL
+SCROLL_AXIS SEED SHOW_MUTATIONS SMOOTH_CELL_SIZE SNAPSHOT_PATH SOAK SPLIT STEP
+SUMMARY_PATH SWEEP SWEEP_OUTPUT TEXT_COLOR UPDATE_PROFILE VIRTUALIZE WARMUP_FRAMES WIDTH
+WINDOWS WORST_FRAME_PATH
+";
+
+/// Whether `name` is a known setting, for every window or as `GRID_BENCH_<n>_FOO` for one.
+fn is_setting(name: &str) -> bool {
+    let Some(setting) = name.strip_prefix("GRID_BENCH_") else {
+        return false;
+    };
+    let window_setting = setting
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .strip_prefix('_');
+    SETTINGS
+        .split_whitespace()
+        .any(|known| known == setting || window_setting == Some(known))
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<HashMap<String, String>, String> {
+    let mut settings = HashMap::new();
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        // Only in flag position; `-h` as a value belongs to the flag before it.
+        if arg == "-h" || arg == "--help" {
+            settings.insert("GRID_BENCH_HELP".to_string(), "1".to_string());
+            continue;
+        }
+        let Some(flag) = arg.strip_prefix("--") else {
+            return Err(format!("unexpected argument {arg:?}"));
+        };
+        let (flag, value) = match flag.split_once('=') {
+            Some((flag, value)) => (flag, value.to_string()),
+            None => match flag.strip_prefix("no-") {
+                Some(flag) => (flag, "0".to_string()),
+                None => match args.next_if(|next| !next.starts_with("--")) {
+                    Some(value) => (flag, value),
+                    None => (flag, "1".to_string()),
+                },
+            },
+        };
+        if flag.is_empty() {
+            return Err(format!("unexpected argument {arg:?}"));
+        }
+        let flag = if flag == "log" { "log-path" } else { flag };
+        let name = format!("GRID_BENCH_{}", flag.replace('-', "_").to_uppercase());
+        if !is_setting(&name) {
+            return Err(format!("unknown flag --{flag}"));
+        }
+        settings.insert(name, value);
+    }
+    Ok(settings)
+}
+
+/// Settings from the `GRID_BENCH_CONFIG` TOML file, keyed by the variable they stand in
+/// for: `cell_size = 32` acts as `GRID_BENCH_CELL_SIZE=32`.
+fn config_file() -> &'static HashMap<String, String> {
+    static CONFIG_FILE: OnceLock<HashMap<String, String>> = OnceLock::new();
+    CONFIG_FILE.get_or_init(|| {
+        let Some(path) = setting("GRID_BENCH_CONFIG") else {
+            return HashMap::new();
+        };
+        match load_config_file(&path) {
+            Ok(settings) => settings,
+            Err(err) => {
+                log::error!("Failed to load GRID_BENCH_CONFIG from {path}: {err}");
+                HashMap::new()
+            }
+        }
+    })
+}
+
+fn load_config_file(path: &str) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let table = std::fs::read_to_string(path)?.parse::<toml::Table>()?;
+    let settings = table
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+                    value.to_string()
+                }
+                _ => {
+                    log::warn!("Ignoring {key} in {path}: expected a string, number or boolean");
+                    return None;
+                }
+            };
+            Some((format!("GRID_BENCH_{}", key.to_uppercase()), value))
+        })
+        .collect();
+    Ok(settings)
+}
+
+/// A setting for the current window, and whether it was given for this window alone.
+/// The command line and environment beat the config file, and within each the window's
+/// `GRID_BENCH_<n>_FOO` beats `GRID_BENCH_FOO`: `2_rows = 500` in the config file gives
+/// window 2 its rows unless `GRID_BENCH_ROWS` or `--rows` is set.
+fn window_env_var(name: &str) -> Option<(String, bool)> {
+    let window_name = name
+        .strip_prefix("GRID_BENCH_")
+        .map(|setting| format!("GRID_BENCH_{}_{}", WINDOW_INDEX.get(), setting));
+    let window_name = window_name.as_deref();
+    let from_file = |name: &str| config_file().get(name).cloned();
+
+    window_name
+        .and_then(setting)
+        .map(|value| (value, true))
+        .or_else(|| setting(name).map(|value| (value, false)))
+        .or_else(|| window_name.and_then(from_file).map(|value| (value, true)))
+        .or_else(|| from_file(name).map(|value| (value, false)))
+}
+
+/// An output file for the current window. `default` goes in the run directory. A shared
+/// setting or `default` gets `_<n>` before its extension in windows after the first, so
+/// windows don't overwrite each other's files; a per-window setting is used as given.
+fn output_path(name: &str, default: &str) -> String {
+    let path = match window_env_var(name) {
+        Some((path, true)) => return path,
+        Some((path, false)) => path,
+        None => match RUN_DIR.get() {
+            Some(dir) => dir.join(default).to_string_lossy().into_owned(),
+            None => default.to_string(),
+        },
+    };
+    let index = WINDOW_INDEX.get();
+    if index == 1 || path == "-" {
+        return path;
+    }
+
+    let path = Path::new(&path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{stem}_{index}.{}", extension.to_string_lossy()),
+        None => format!("{stem}_{index}"),
+    };
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// `GRID_BENCH_SPLIT=<w>,<w>`: the cell widths of the left and right grids of a split
+/// window. The panes are built as windows 1 and 2, so `GRID_BENCH_<n>_*` overrides work
+/// per pane as they do per window. Output files are the window's, written by pane 1.
+fn split_cell_sizes() -> Option<[f32; 2]> {
+    let sizes = shared_env_var("GRID_BENCH_SPLIT")?
+        .split(',')
+        .map(|size| size.trim().parse::<f32>().ok().filter(|&size| size >= 8.0))
+        .collect::<Option<Vec<_>>>();
+    match sizes.as_deref() {
+        Some(&[left, right]) => Some([left, right]),
+        _ => {
+            log::error!("GRID_BENCH_SPLIT wants two cell sizes of at least 8, like 24,48");
+            None
+        }
+    }
+}
+
+fn env_bool(name: &str, default: bool) -> bool {
+    env_var(name)
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(default)
+}
+
+fn env_usize(name: &str, default: usize) -> usize {
+    env_var(name)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+fn env_usize_opt(name: &str) -> Option<usize> {
+    env_var(name).and_then(|v| v.parse().ok())
+}
+
+fn env_f32(name: &str, default: f32) -> f32 {
+    env_var(name)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+const DEFAULT_ROWS: usize = if cfg!(debug_assertions) { 300 } else { 800 };
+const DEFAULT_CELL_SIZE: f32 = 42.0;
+const DEFAULT_WIDTH: f32 = 960.0;
+const DEFAULT_HEIGHT: f32 = 1080.0;
+const DEFAULT_REFRESH_HZ: f32 = 60.0;
+/// Overlay background opacities cycled by the `o` key, starting from the first.
+const OVERLAY_OPACITIES: [f32; 4] = [0.7, 0.4, 0.15, 0.95];
+#[cfg(feature = "fiber")]
+const DEFAULT_WARMUP_FRAMES: usize = 30;
+const DEFAULT_RAMP_FRAMES: usize = 10;
+/// Frames the ramp waits before its first step, so the FPS history fills first.
+const RAMP_WARMUP_FRAMES: usize = 60;
+/// Without `GRID_BENCH_RAMP_MIN_FPS`, the ramp stops below this share of the target rate.
+const RAMP_TARGET_FRACTION: f32 = 0.9;
+/// Frames between the adaptive grid's steps, so the averaged FPS can follow each one.
+const ADAPTIVE_INTERVAL_FRAMES: usize = 30;
+/// Share of the relative frame-time error the adaptive grid corrects in one step.
+const ADAPTIVE_GAIN: f64 = 0.5;
+/// Largest relative frame-time error acted on in one step, in either direction.
+const ADAPTIVE_MAX_ERROR: f64 = 0.5;
+/// The error assumed at the refresh rate, where the interval is vsync's and only shows
+/// there is room to grow.
+const ADAPTIVE_REFRESH_ERROR: f64 = 0.1;
+/// Steps whose row counts must agree for the adaptive grid to count as settled.
+const ADAPTIVE_SETTLE_STEPS: usize = 6;
+/// How far those row counts may stray from their average, as a fraction.
+const ADAPTIVE_SETTLE_FRACTION: f64 = 0.1;
+/// Frames a burst resize runs before restoring the window size.
+const BURST_RESIZE_FRAMES: usize = 240;
+const BURST_RESIZE_PERIOD_FRAMES: f32 = 40.0;
+/// How far a burst resize swings the window from its starting size, as a fraction.
+const BURST_RESIZE_AMPLITUDE: f32 = 0.3;
+/// How far the overlay's window buttons move either edge.
+const WINDOW_SIZE_STEP: f32 = 100.0;
+/// Smallest window the overlay's buttons will shrink to, which still fits the overlay.
+const MIN_WINDOW_WIDTH: f32 = 480.0;
+const MIN_WINDOW_HEIGHT: f32 = 360.0;
+#[cfg(feature = "fiber")]
+const DEFAULT_LOG_FLUSH_FRAMES: usize = 60;
+#[cfg(feature = "fiber")]
+const LOG_BUFFER_BYTES: usize = 256 * 1024;
+/// Which GPUI the build uses, recorded in the frame log and `config.json`.
+const BACKEND: &str = if cfg!(feature = "fiber") {
+    "fiber"
+} else {
+    "upstream"
+};
+const PROFILE: &str = if cfg!(debug_assertions) {
+    "debug"
+} else {
+    "release"
+};
+/// Opening frames left out of the worst frame, which would otherwise always be the first.
+const WORST_FRAME_WARMUP_FRAMES: u64 = 30;
+/// Opening frames the FPS alarm ignores while the rolling average fills up.
+const FPS_ALARM_WARMUP_FRAMES: u64 = 60;
+/// Recent actions kept for the FPS alarm's report.
+const ACTION_HISTORY: usize = 16;
+/// Rotated frame logs kept under `GRID_BENCH_LOG_MAX_MB` without `GRID_BENCH_LOG_KEEP`.
+#[cfg(feature = "fiber")]
+const DEFAULT_LOG_KEEP: usize = 5;
+#[cfg(feature = "fiber")]
+const UPLOAD_HISTORY: usize = 60;
+/// Paint replay ratios kept for `FpsView::missed_replay`.
+#[cfg(feature = "fiber")]
+const REPLAY_HISTORY: usize = 30;
+/// Consecutive near-zero frames at the end of the history that count as a drop.
+#[cfg(feature = "fiber")]
+const REPLAY_DROP_FRAMES: usize = 5;
+#[cfg(feature = "fiber")]
+const REPLAY_HEALTHY_RATIO: f64 = 0.5;
+#[cfg(feature = "fiber")]
+const REPLAY_MISSED_RATIO: f64 = 0.05;
+/// Frames of hitbox rebuild counts kept for the HUD.
+#[cfg(feature = "fiber")]
+const HITBOX_HISTORY: usize = 60;
+/// Share of static frames (nothing laid out) with rebuilt hitboxes that turns the line red.
+#[cfg(feature = "fiber")]
+const HITBOX_STATIC_ALERT_RATIO: f64 = 0.1;
+#[cfg(feature = "fiber")]
+const UPLOAD_WARN_BYTES: u64 = 64 * 1024;
+#[cfg(feature = "fiber")]
+const UPLOAD_ALERT_BYTES: u64 = 1024 * 1024;
+const DEFAULT_CELL_GAP: f32 = 4.0;
+const MAX_CELL_GAP: f32 = 32.0;
+const GRID_PADDING: f32 = 16.0;
+/// Height of the header row and width of the header column.
+const HEADER_SIZE: f32 = 24.0;
+const FOOTER_HEIGHT: f32 = 24.0;
+/// Height of the bar comparing the panes of a split window.
+const SPLIT_BAR_HEIGHT: f32 = 24.0;
+/// Matches `top_2` and friends.
+const OVERLAY_INSET: f32 = 8.0;
+/// Draw order of the overlay among deferred elements. Anything the grid defers should
+/// stay below this, so the HUD is never covered.
+const OVERLAY_PRIORITY: usize = 1_000;
+const DEFAULT_FRAME_HISTORY: usize = 60;
+/// Weight of the newest frame time in the moving average without `GRID_BENCH_EMA_ALPHA`.
+const DEFAULT_EMA_ALPHA: f32 = 0.1;
+const LOW_FPS_THRESHOLD: f64 = 30.0;
+/// The latest frame time is healthy below this and over budget above `FRAME_TIME_ALERT_MS`.
+const FRAME_TIME_WARN_MS: f64 = 12.0;
+const FRAME_TIME_ALERT_MS: f64 = 16.6;
+/// Below target FPS, grid render CPU time over this share of the frame interval reads as
+/// CPU-bound, and under `GPU_BOUND_CPU_SHARE` as GPU-bound.
+const CPU_BOUND_CPU_SHARE: f64 = 0.75;
+const GPU_BOUND_CPU_SHARE: f64 = 0.25;
+const SPARKLINE_WIDTH: f32 = 60.0;
+const SPARKLINE_HEIGHT: f32 = 20.0;
+#[cfg(feature = "rss")]
+const RSS_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+/// Shortest time between rewrites of `GRID_BENCH_WORST_FRAME_PATH`.
+const WORST_FRAME_WRITE_INTERVAL: Duration = Duration::from_secs(1);
+const HISTOGRAM_BUCKETS: usize = 10;
+const HISTOGRAM_MAX_MS: f64 = 33.0;
+/// Row step sizes cycled by the step controls.
+const STEP_PRESETS: [usize; 4] = [1, 5, 10, 50];
+/// How long a cell size change takes under `GRID_BENCH_SMOOTH_CELL_SIZE`.
+const CELL_SIZE_TRANSITION: Duration = Duration::from_millis(200);
+/// Grid renders over which a changed cell's tint fades under `GRID_BENCH_SHOW_MUTATIONS`.
+const MUTATION_FADE_FRAMES: u32 = 10;
+/// Hue of that tint, a magenta none of the cell colors settle on for long.
+const MUTATION_TINT_HUE: f32 = 300.0 / 360.0;
+/// Ctrl-scroll distance that changes the cell size by one step.
+const ZOOM_STEP_PX: f32 = 20.0;
+/// Extra rows built above and below the viewport when virtualizing.
+const OVERSCAN_ROWS: usize = 2;
+const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+/// Most frames a soak test waits between actions; each gap is drawn from `1..=` this.
+const SOAK_MAX_GAP_FRAMES: u64 = 30;
+/// A soak test removes rows instead of adding them past this many.
+const SOAK_MAX_ROWS: usize = 1_000;
+const LONG_LABEL_WORDS: usize = 8;
+/// `GRID_BENCH_LABEL=mono` pads cell numbers with zeros to this many digits.
+const MONO_LABEL_DIGITS: usize = 5;
+/// Hue difference between neighbouring characters under `GRID_BENCH_TEXT_COLOR=rainbow`.
+const RAINBOW_HUE_STEP: u32 = 47;
+/// Appended to each label under `GRID_BENCH_TEXT_COLOR=rainbow`: a color glyph, drawn as a
+/// polychrome sprite where the platform has an emoji font.
+const RAINBOW_EMOJI: &str = "\u{1f308}";
+/// A monospace font that ships with each platform.
+const MONO_FONT_FAMILY: &str = if cfg!(target_os = "macos") {
+    "Menlo"
+} else if cfg!(target_os = "windows") {
+    "Consolas"
+} else {
+    "DejaVu Sans Mono"
+};
+/// The two hues of the `stripes` and `checker` patterns; `solid` uses the first.
+const PATTERN_HUES: [u32; 2] = [210, 30];
+/// Cells per side of each block in the `nested_grids` layout.
+const NESTED_GRID_SIZE: usize = 4;
+const NESTED_GRID_GAP: f32 = 1.0;
+const LOREM_WORDS: [&str; 19] = [
+    "lorem",
+    "ipsum",
+    "dolor",
+    "sit",
+    "amet",
+    "consectetur",
+    "adipiscing",
+    "elit",
+    "sed",
+    "do",
+    "eiusmod",
+    "tempor",
+    "incididunt",
+    "ut",
+    "labore",
+    "et",
+    "dolore",
+    "magna",
+    "aliqua",
+];
+const CELL_IMAGE: &str = "cell.png";
+const CELL_ICON: &str = "cell.svg";
+
+/// Scales applied to `cell_size` in `CellSizeMode::Varied`. All are at most 1.0 so
+/// `calculate_col_count` still fits every row in the window.
+const VARIED_CELL_SCALES: [f32; 5] = [1.0, 0.5, 0.75, 0.625, 0.875];
+
+/// Assets compiled into the binary for the image and icon cell content modes.
+struct Assets;
+
+impl AssetSource for Assets {
+    fn load(&self, path: &str) -> gpui::Result<Option<Cow<'static, [u8]>>> {
+        Ok(match path {
+            CELL_IMAGE => Some(Cow::Borrowed(include_bytes!("../assets/cell.png"))),
+            CELL_ICON => Some(Cow::Borrowed(include_bytes!("../assets/cell.svg"))),
+            _ => None,
+        })
+    }
+
+    fn list(&self, _path: &str) -> gpui::Result<Vec<SharedString>> {
+        Ok(vec![CELL_IMAGE.into(), CELL_ICON.into()])
+    }
+}
+
+/// What each cell draws inside its background quad.
+#[derive(Clone, Copy, PartialEq)]
+enum CellContent {
+    /// The cell number as text (shaped glyphs, monochrome sprites).
+    Index,
+    /// The cell number and a few words of wrapped, clipped text (`GRID_BENCH_LABEL=long`),
+    /// to load text layout and the glyph atlas.
+    LongLabel,
+    /// The cell number zero-padded and right-aligned in a monospace font
+    /// (`GRID_BENCH_LABEL=mono`), so every cell shapes the same few glyphs.
+    MonoLabel,
+    /// Nothing, to measure the background quads alone.
+    Empty,
+    /// A bundled raster image (polychrome sprites).
+    Image,
+    /// A bundled SVG icon (monochrome sprites without text shaping).
+    Icon,
+}
+
+impl CellContent {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_CELL_CONTENT").as_deref() {
+            Some("empty") => Self::Empty,
+            Some("image") => Self::Image,
+            Some("icon") => Self::Icon,
+            _ => match env_var("GRID_BENCH_LABEL").as_deref() {
+                Some("long") => Self::LongLabel,
+                Some("mono") => Self::MonoLabel,
+                _ => Self::Index,
+            },
+        }
+    }
+
+    fn element(
+        self,
+        cell_num: usize,
+        cell_width: f32,
+        cell_height: f32,
+        text_color: TextColor,
+    ) -> Option<gpui::AnyElement> {
+        let icon_size = px(cell_width.min(cell_height) * 0.6);
+        match self {
+            Self::Index => Some(text_color.label(format!("{}", cell_num))),
+            // Sized to the cell so the label wraps and clips instead of growing it.
+            Self::LongLabel => Some(
+                div()
+                    .w(px(cell_width))
+                    .h(px(cell_height))
+                    .overflow_hidden()
+                    .child(text_color.label(long_label(cell_num)))
+                    .into_any_element(),
+            ),
+            Self::MonoLabel => Some(
+                div()
+                    .w_full()
+                    .px_1()
+                    .text_right()
+                    .font_family(MONO_FONT_FAMILY)
+                    .child(text_color.label(format!(
+                        "{:0width$}",
+                        cell_num,
+                        width = MONO_LABEL_DIGITS
+                    )))
+                    .into_any_element(),
+            ),
+            Self::Empty => None,
+            Self::Image => Some(img(CELL_IMAGE).size(icon_size).into_any_element()),
+            Self::Icon => Some(
+                svg()
+                    .path(CELL_ICON)
+                    .size(icon_size)
+                    .text_color(gpui::white())
+                    .into_any_element(),
+            ),
+        }
+    }
+}
+
+/// How cell labels are colored (`GRID_BENCH_TEXT_COLOR`).
+#[derive(Clone, Copy, PartialEq)]
+enum TextColor {
+    White,
+    /// Every character in its own hue (`rainbow`), so each label shapes into a run per
+    /// character. GPUI tints glyphs from the monochrome atlas whatever their color, so
+    /// each label also ends in `RAINBOW_EMOJI` to add a polychrome sprite.
+    Rainbow,
+}
+
+impl TextColor {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_TEXT_COLOR").as_deref() {
+            Some("rainbow") => Self::Rainbow,
+            _ => Self::White,
+        }
+    }
+
+    fn label(self, text: String) -> gpui::AnyElement {
+        match self {
+            Self::White => text.into_any_element(),
+            Self::Rainbow => {
+                let highlights = text
+                    .char_indices()
+                    .enumerate()
+                    .map(|(index, (start, char))| {
+                        let hue = index as u32 * RAINBOW_HUE_STEP % 360;
+                        let style = HighlightStyle {
+                            color: Some(hsv_to_rgb(hue, 60, 100)),
+                            ..Default::default()
+                        };
+                        (start..start + char.len_utf8(), style)
+                    })
+                    .collect::<Vec<_>>();
+                StyledText::new(text + RAINBOW_EMOJI)
+                    .with_highlights(highlights)
+                    .into_any_element()
+            }
+        }
+    }
+}
+
+/// The cell number followed by `LONG_LABEL_WORDS` words of lorem ipsum, starting from a
+/// word picked by hashing the number.
+fn long_label(cell_num: usize) -> String {
+    let start = ((cell_num as u64).wrapping_mul(2_654_435_761) >> 16) as usize;
+    let words = (0..LONG_LABEL_WORDS)
+        .map(|i| LOREM_WORDS[(start + i) % LOREM_WORDS.len()])
+        .collect::<Vec<_>>();
+    format!("{} {}", cell_num, words.join(" "))
+}
+
+/// Wraps `content` in `depth` plain divs, deepening the tree without changing what is drawn.
+fn nested(depth: usize, content: Option<gpui::AnyElement>) -> Option<gpui::AnyElement> {
+    (0..depth).fold(content, |content, _| {
+        Some(div().children(content).into_any_element())
+    })
+}
+
+/// Totals from a previous run's `SessionSummary` report, loaded from `GRID_BENCH_BASELINE`.
+/// The file holds `key=value` lines; unknown keys are ignored.
+struct Baseline {
+    frames: u64,
+    avg_fps: f64,
+    avg_upload_bytes: f64,
+}
+
+impl Baseline {
+    fn from_env() -> Option<Self> {
+        let path = env_var("GRID_BENCH_BASELINE")?;
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                log::warn!("Failed to read baseline {path}: {err}");
+                return None;
+            }
+        };
+        let field = |key: &str| {
+            contents.lines().find_map(|line| {
+                let (name, value) = line.split_once('=')?;
+                if name.trim() == key {
+                    value.trim().parse::<f64>().ok()
+                } else {
+                    None
+                }
+            })
+        };
+
+        let Some(avg_fps) = field("avg_fps") else {
+            log::warn!("Baseline {path} has no avg_fps");
+            return None;
+        };
+        Some(Self {
+            frames: field("frames").unwrap_or(0.0) as u64,
+            avg_fps,
+            avg_upload_bytes: field("avg_upload_bytes").unwrap_or(0.0),
+        })
+    }
+}
+
+/// Extra styling on every cell, to measure its cost in quads and shadow passes.
+#[derive(Clone, Copy, PartialEq)]
+enum CellDecoration {
+    None,
+    Border,
+    Shadow,
+    Both,
+}
+
+impl CellDecoration {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_CELL_DECORATION").as_deref() {
+            Some("border") => Self::Border,
+            Some("shadow") => Self::Shadow,
+            Some("both") => Self::Both,
+            _ => Self::None,
+        }
+    }
+
+    fn border(self) -> bool {
+        matches!(self, Self::Border | Self::Both)
+    }
+
+    fn shadow(self) -> bool {
+        matches!(self, Self::Shadow | Self::Both)
+    }
+}
+
+/// Row styles for `GRID_BENCH_CELL_CONTENT=mixed`: text, image, empty and decorated rows,
+/// so every per-cell path is built in the same frame.
+const MIXED_ROWS: [(CellContent, CellDecoration); 4] = [
+    (CellContent::Index, CellDecoration::None),
+    (CellContent::Image, CellDecoration::None),
+    (CellContent::Empty, CellDecoration::None),
+    (CellContent::Index, CellDecoration::Both),
+];
+
+/// The outline of each cell's background (`GRID_BENCH_CELL_SHAPE`).
+#[derive(Clone, Copy, PartialEq)]
+enum CellShape {
+    Square,
+    Rounded,
+    /// A corner radius of half the cell's size.
+    Circle,
+}
+
+impl CellShape {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_CELL_SHAPE").as_deref() {
+            Some("square") => Self::Square,
+            Some("circle") => Self::Circle,
+            _ => Self::Rounded,
+        }
+    }
+
+    fn apply<E: Styled>(self, element: E, cell_size: f32) -> E {
+        match self {
+            Self::Square => element,
+            Self::Rounded => element.rounded_sm(),
+            Self::Circle => element.rounded(px(cell_size / 2.0)),
+        }
+    }
+}
+
+/// Which cells light up around the hovered one (`GRID_BENCH_HOVER_MODE`).
+#[derive(Clone, Copy, PartialEq)]
+enum HoverMode {
+    /// Only the hovered cell, through its own hover style.
+    Cell,
+    Row,
+    Column,
+    /// The hovered cell's row and column.
+    Cross,
+}
+
+impl HoverMode {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_HOVER_MODE").as_deref() {
+            Some("row") => Self::Row,
+            Some("col") => Self::Column,
+            Some("cross") => Self::Cross,
+            _ => Self::Cell,
+        }
+    }
+
+    /// Whether `cell_num` is highlighted while `hovered` is under the pointer. `Cell`
+    /// leaves this to the hover style, so it never highlights.
+    fn highlights(self, hovered: usize, cell_num: usize, col_count: usize) -> bool {
+        let same_row = hovered / col_count == cell_num / col_count;
+        let same_col = hovered % col_count == cell_num % col_count;
+        match self {
+            Self::Cell => false,
+            Self::Row => same_row,
+            Self::Column => same_col,
+            Self::Cross => same_row || same_col,
+        }
+    }
+}
+
+/// Nearest-rank percentile of an ascending, non-empty slice (same indexing as `bench.py`).
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let n = sorted.len();
+    sorted[((n as f64 * p) as usize).min(n - 1)]
+}
+
+#[derive(Clone, Copy, Default)]
+struct FramePercentiles {
+    p50: f64,
+    p95: f64,
+    p99: f64,
+}
+
+/// Whole-run statistics, as opposed to `FpsCounter`'s rolling window. The report is
+/// written once, when the app quits or the owning view is dropped.
+struct SessionSummary {
+    /// `GRID_BENCH_SUMMARY_PATH`, or `None` when another view reports for the window.
+    path: Option<String>,
+    frames: u64,
+    /// Every inter-frame interval of the run, in milliseconds.
+    frame_times_ms: Vec<f64>,
+    #[cfg(feature = "fiber")]
+    upload_bytes: u64,
+    #[cfg(feature = "fiber")]
+    peak_pool_segments: usize,
+    /// Total cost and count of frames without (`[0]`) and with (`[1]`) the HUD, under
+    /// `GRID_BENCH_HUD_AB`.
+    hud_frames: [(f64, u64); 2],
+    reported: bool,
+}
+
+impl SessionSummary {
+    fn new(path: Option<String>) -> Self {
+        Self {
+            path,
+            frames: 0,
+            frame_times_ms: Vec::new(),
+            #[cfg(feature = "fiber")]
+            upload_bytes: 0,
+            #[cfg(feature = "fiber")]
+            peak_pool_segments: 0,
+            hud_frames: [(0.0, 0); 2],
+            reported: false,
+        }
+    }
+
+    /// `frame_ms` is the interval since the previous frame, or 0 when there is none.
+    fn record_frame(&mut self, frame_ms: f64) {
+        if frame_ms > 0.0 {
+            self.frame_times_ms.push(frame_ms);
+        }
+        self.frames += 1;
+    }
+
+    fn record_hud_frame(&mut self, hud: bool, frame_ms: f64) {
+        if frame_ms > 0.0 {
+            let (total_ms, frames) = &mut self.hud_frames[usize::from(hud)];
+            *total_ms += frame_ms;
+            *frames += 1;
+        }
+    }
+
+    /// Average frame cost with the HUD minus without it, once there are frames of both.
+    fn hud_cost_ms(&self) -> Option<f64> {
+        let avg = |(total_ms, frames): (f64, u64)| (frames > 0).then(|| total_ms / frames as f64);
+        Some(avg(self.hud_frames[1])? - avg(self.hud_frames[0])?)
+    }
+
+    #[cfg(feature = "fiber")]
+    fn record_diagnostics(&mut self, diag: &gpui::FrameDiagnostics) {
+        self.upload_bytes += diag.estimated_instance_upload_bytes as u64;
+        self.peak_pool_segments = self.peak_pool_segments.max(diag.total_pool_segments);
+    }
+
+    fn avg_fps(&self) -> f64 {
+        let total_ms = self.frame_times_ms.iter().sum::<f64>();
+        if total_ms > 0.0 {
+            self.frame_times_ms.len() as f64 * 1000.0 / total_ms
+        } else {
+            0.0
+        }
+    }
+
+    #[cfg(feature = "fiber")]
+    fn avg_upload_bytes(&self) -> f64 {
+        self.upload_bytes as f64 / self.frames.max(1) as f64
+    }
+
+    /// `key=value` lines, readable back as a `Baseline`.
+    fn report(&self) -> String {
+        let mut sorted = self.frame_times_ms.clone();
+        sorted.sort_by(f64::total_cmp);
+        let (min_ms, max_ms, p99_ms) = match (sorted.first(), sorted.last()) {
+            (Some(&min), Some(&max)) => (min, max, percentile(&sorted, 0.99)),
+            _ => (0.0, 0.0, 0.0),
+        };
+        let avg_ms = sorted.iter().sum::<f64>() / sorted.len().max(1) as f64;
+        let fps = |ms: f64| if ms > 0.0 { 1000.0 / ms } else { 0.0 };
+
+        let mut lines = vec![
+            format!("frames={}", self.frames),
+            format!("avg_fps={:.2}", self.avg_fps()),
+            format!("min_fps={:.2}", fps(max_ms)),
+            format!("max_fps={:.2}", fps(min_ms)),
+            format!("p99_fps={:.2}", fps(p99_ms)),
+            format!("avg_frame_ms={:.3}", avg_ms),
+            format!("min_frame_ms={:.3}", min_ms),
+            format!("max_frame_ms={:.3}", max_ms),
+            format!("p99_frame_ms={:.3}", p99_ms),
+        ];
+        #[cfg(feature = "fiber")]
+        lines.extend([
+            format!("avg_upload_bytes={:.0}", self.avg_upload_bytes()),
+            format!("peak_pool_segments={}", self.peak_pool_segments),
+        ]);
+        if let Some(hud_cost_ms) = self.hud_cost_ms() {
+            lines.push(format!("hud_cost_ms={:.3}", hud_cost_ms));
+        }
+        lines.join("\n")
+    }
+
+    fn write_report(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if self.reported || self.frames == 0 {
+            return;
+        }
+        self.reported = true;
+
+        let report = self.report();
+        println!("{report}");
+        match std::fs::write(path, report + "\n") {
+            Ok(()) => log::info!("Wrote frame summary to {path}"),
+            Err(err) => log::error!("Failed to write frame summary to {path}: {err}"),
+        }
+    }
+}
+
+impl Drop for SessionSummary {
+    fn drop(&mut self) {
+        self.write_report();
+    }
+}
+
+/// Resident set size of this process, re-read at most once per `RSS_SAMPLE_INTERVAL`.
+/// It's the OS's figure for pages currently in RAM, so treat it as an estimate of
+/// memory use rather than an allocation count.
+#[cfg(feature = "rss")]
+struct RssSampler {
+    system: sysinfo::System,
+    pid: Option<sysinfo::Pid>,
+    sampled_at: Option<Instant>,
+    bytes: u64,
+}
+
+#[cfg(feature = "rss")]
+impl RssSampler {
+    fn new() -> Self {
+        Self {
+            system: sysinfo::System::new(),
+            pid: sysinfo::get_current_pid().ok(),
+            sampled_at: None,
+            bytes: 0,
+        }
+    }
+
+    fn sample(&mut self) {
+        if self
+            .sampled_at
+            .is_some_and(|sampled_at| sampled_at.elapsed() < RSS_SAMPLE_INTERVAL)
+        {
+            return;
+        }
+        self.sampled_at = Some(Instant::now());
+
+        let Some(pid) = self.pid else {
+            return;
+        };
+        self.system.refresh_processes_specifics(
+            sysinfo::ProcessesToUpdate::Some(&[pid]),
+            false,
+            sysinfo::ProcessRefreshKind::nothing().with_memory(),
+        );
+        if let Some(process) = self.system.process(pid) {
+            self.bytes = process.memory();
+        }
+    }
+}
+
+struct FpsCounter {
+    times: VecDeque<Instant>,
+    history: usize,
+    fps: f64,
+    budget_ms: f64,
+    /// Intervals in `times` that took longer than `budget_ms`.
+    dropped: usize,
+    /// Weight of each new frame time in `ema_ms`.
+    ema_alpha: f64,
+    /// Exponential moving average of the frame time, quicker to follow a change than the
+    /// windowed average and steadier than the latest interval.
+    ema_ms: Option<f64>,
+}
+
+impl FpsCounter {
+    fn new(history: usize, budget_ms: f64, ema_alpha: f64) -> Self {
+        let history = history.max(2);
+        Self {
+            times: VecDeque::with_capacity(history + 1),
+            history,
+            fps: 0.0,
+            budget_ms,
+            dropped: 0,
+            ema_alpha,
+            ema_ms: None,
+        }
+    }
+
+    fn record(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.times.back()
+            && self.over_budget(*last, now)
+        {
+            self.dropped += 1;
+        }
+        self.times.push_back(now);
+        if self.times.len() >= 2 {
+            let frame_ms = self.last_frame_ms();
+            self.ema_ms = Some(match self.ema_ms {
+                Some(ema_ms) => ema_ms + self.ema_alpha * (frame_ms - ema_ms),
+                None => frame_ms,
+            });
+        }
+
+        while self.times.len() > self.history {
+            if let (Some(oldest), Some(next)) = (self.times.pop_front(), self.times.front())
+                && self.over_budget(oldest, *next)
+            {
+                self.dropped -= 1;
+            }
+        }
+
+        if self.times.len() >= 2 {
+            if let Some(oldest) = self.times.front() {
+                let elapsed = now.duration_since(*oldest).as_secs_f64();
+                self.fps = (self.times.len() - 1) as f64 / elapsed;
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.times.clear();
+        self.fps = 0.0;
+        self.dropped = 0;
+        self.ema_ms = None;
+    }
+
+    fn ema_fps(&self) -> f64 {
+        match self.ema_ms {
+            Some(ema_ms) if ema_ms > 0.0 => 1000.0 / ema_ms,
+            _ => 0.0,
+        }
+    }
+
+    fn over_budget(&self, start: Instant, end: Instant) -> bool {
+        end.duration_since(start).as_secs_f64() * 1000.0 > self.budget_ms
+    }
+
+    fn dropped_frames(&self) -> usize {
+        self.dropped
+    }
+
+    fn interval_count(&self) -> usize {
+        self.times.len().saturating_sub(1)
+    }
+
+    fn last_frame_ms(&self) -> f64 {
+        match (self.times.iter().nth_back(1), self.times.back()) {
+            (Some(prev), Some(last)) => last.duration_since(*prev).as_secs_f64() * 1000.0,
+            _ => 0.0,
+        }
+    }
+
+    fn worst_frame_ms(&self) -> f64 {
+        self.frame_times_ms().into_iter().fold(0.0, f64::max)
+    }
+
+    /// Inter-frame intervals in the history window, in milliseconds.
+    fn frame_times_ms(&self) -> Vec<f64> {
+        self.times
+            .iter()
+            .zip(self.times.iter().skip(1))
+            .map(|(prev, next)| next.duration_since(*prev).as_secs_f64() * 1000.0)
+            .collect()
+    }
+
+    /// Frame-time counts in `buckets` equal ranges over `0..max_ms`; slower frames land
+    /// in the last bucket.
+    fn histogram(&self, buckets: usize, max_ms: f64) -> Vec<usize> {
+        let mut counts = vec![0; buckets.max(1)];
+        let last = counts.len() - 1;
+        for ms in self.frame_times_ms() {
+            let bucket = (ms / max_ms * counts.len() as f64) as usize;
+            counts[bucket.min(last)] += 1;
+        }
+        counts
+    }
+
+    fn percentiles(&self) -> FramePercentiles {
+        let mut deltas = self.frame_times_ms();
+        if deltas.is_empty() {
+            return FramePercentiles::default();
+        }
+
+        deltas.sort_by(f64::total_cmp);
+
+        FramePercentiles {
+            p50: percentile(&deltas, 0.50),
+            p95: percentile(&deltas, 0.95),
+            p99: percentile(&deltas, 0.99),
+        }
+    }
+
+    /// FPS implied by the mean of the slowest `fraction` of frames in the window
+    /// (`0.01` for the "1% low"). With the default 60-frame history both the 1% and
+    /// 0.1% lows collapse to the single worst frame, so at least one frame is always used.
+    fn low_fps(&self, fraction: f64) -> f64 {
+        let mut deltas = self.frame_times_ms();
+        if deltas.is_empty() {
+            return 0.0;
+        }
+
+        deltas.sort_by(|a, b| b.total_cmp(a));
+        let count = ((deltas.len() as f64 * fraction).ceil() as usize).clamp(1, deltas.len());
+        let mean_ms = deltas[..count].iter().sum::<f64>() / count as f64;
+        1000.0 / mean_ms
+    }
+}
+
+struct FpsView {
+    render_fps: FpsCounter,
+    /// Records every frame, frozen or not, for the frame log, the session summary, the
+    /// worst frame and the FPS alarm.
+    log_fps: FpsCounter,
+    frame_fps: FpsCounter,
+    started_at: Instant,
+    session: SessionSummary,
+    baseline: Option<Baseline>,
+    /// The display refresh rate that FPS is measured against.
+    target_hz: f32,
+    /// No rate was given, so `target_hz` is `DEFAULT_REFRESH_HZ`.
+    target_assumed: bool,
+    /// Show only the FPS line.
+    compact: bool,
+    /// Show FPS from the latest frame interval instead of the rolling average.
+    instant_fps: bool,
+    /// Show FPS with an extra decimal place, times in microseconds and sizes in bytes, so
+    /// rounding doesn't hide small differences between runs.
+    precise: bool,
+    /// Stop requesting frames so the HUD holds its current numbers.
+    paused: bool,
+    /// Keep rendering but stop recording frames into the HUD, so the FPS numbers hold still
+    /// while the grid keeps moving. The frame log and the summary still count every frame.
+    frozen: bool,
+    /// Mark the next frame in the frame log and on stdout.
+    marker_pending: bool,
+    batch_append: Option<BatchAppend>,
+    worst_frame: Option<WorstFrame>,
+    worst_frame_file: Option<WorstFrameFile>,
+    /// The latest frame number, which stamps actions as they are recorded.
+    frame: u64,
+    /// Keys pressed and script or soak actions run, with the frame each followed.
+    recent_actions: VecDeque<(u64, String)>,
+    /// `GRID_BENCH_FPS_ALARM`: the render FPS below which the recent actions and the
+    /// frame's diagnostics are appended to `fps_alarm_path`.
+    fps_alarm: Option<f64>,
+    fps_alarm_path: String,
+    /// The FPS is below the alarm threshold; it must recover before the alarm fires again.
+    fps_alarm_raised: bool,
+    /// Titles of the HUD sections collapsed down to their header.
+    collapsed_sections: HashSet<&'static str>,
+    /// `GRID_BENCH_HUD_AB`: leave the HUD out of every other frame and compare the costs
+    /// of frames with and without it, to tell the HUD's cost from the grid's.
+    hud_ab: bool,
+    /// Whether the grid leaves the whole overlay out of this frame, building only this
+    /// view so it keeps logging.
+    hud_hidden: bool,
+    /// Whether the previous frame left the overlay out; it's the frame this render's
+    /// diagnostics describe.
+    hud_was_hidden: bool,
+    /// Minimum time between frames under `GRID_BENCH_FPS_CAP`.
+    frame_interval: Option<Duration>,
+    /// When the frame callback may next emit a frame under the cap.
+    next_frame_at: Instant,
+    /// CPU time of the latest `GridBench::render`, reported by the grid itself.
+    grid_render_time: Duration,
+    /// The grid's rows, columns and cell size as of its latest render.
+    grid_size: (usize, usize, f32),
+    /// Cells the grid built in its latest render, which per-cell costs divide by.
+    cells_built: usize,
+    /// `cells_built` as of the previous frame, the one this render's diagnostics describe.
+    #[cfg(feature = "fiber")]
+    prev_cells_built: usize,
+    /// The grid's scroll position as of its latest render, in positive pixels.
+    grid_scroll: Point<Pixels>,
+    #[cfg(feature = "rss")]
+    rss: RssSampler,
+    /// `None` in the right pane of a split window, whose frames the left pane logs.
+    #[cfg(feature = "fiber")]
+    frame_log: Option<std::sync::Arc<std::sync::Mutex<FrameLog>>>,
+    #[cfg(feature = "fiber")]
+    upload_history: VecDeque<u64>,
+    /// Share of painted fibers that were replayed, for recent frames that painted any.
+    #[cfg(feature = "fiber")]
+    replay_history: VecDeque<f64>,
+    /// Hitboxes rebuilt in each recent frame, and whether the frame laid nothing out.
+    #[cfg(feature = "fiber")]
+    hitbox_history: VecDeque<(usize, bool)>,
+    /// High-water marks since startup or the last reset.
+    #[cfg(feature = "fiber")]
+    peak_upload_bytes: u64,
+    #[cfg(feature = "fiber")]
+    peak_pool_segments: usize,
+    /// The previous frame's counts, for showing what changed.
+    #[cfg(feature = "fiber")]
+    prev_counts: Option<FrameCounts>,
+    /// Show each count as the change from the previous frame instead of its value.
+    #[cfg(feature = "fiber")]
+    count_deltas: bool,
+}
+
+impl FpsView {
+    fn new(cx: &mut Context<Self>) -> Self {
+        // Dropping the view flushes the log and writes the summary when the window
+        // closes; quitting may not drop it.
+        cx.on_app_quit(|fps_view, _| {
+            #[cfg(feature = "fiber")]
+            if let Some(frame_log) = &fps_view.frame_log {
+                lock_unpoisoned(frame_log).flush();
+            }
+            fps_view.session.write_report();
+            if let Some(worst_frame_file) = &mut fps_view.worst_frame_file {
+                worst_frame_file.flush();
+            }
+            async {}
+        })
+        .detach();
+
+        // Both panes of a split window see the same frames, so only the left one writes
+        // the window's frame log, summary, worst frame and FPS alarms.
+        let logs_window = split_cell_sizes().is_none() || WINDOW_INDEX.get() == 1;
+
+        // GPUI doesn't expose the display's refresh rate, so it has to be given.
+        let refresh_hz = env_var("GRID_BENCH_REFRESH_HZ")
+            .and_then(|v| v.parse::<f32>().ok())
+            .filter(|&hz| hz > 0.0);
+        let target_hz = refresh_hz.unwrap_or(DEFAULT_REFRESH_HZ);
+
+        let budget_ms = env_f32("GRID_BENCH_FRAME_BUDGET_MS", 1000.0 / target_hz) as f64;
+        let history = env_usize("GRID_BENCH_FRAME_HISTORY", DEFAULT_FRAME_HISTORY);
+        let fps_cap = env_f32("GRID_BENCH_FPS_CAP", 0.0);
+        let ema_alpha = env_f32("GRID_BENCH_EMA_ALPHA", DEFAULT_EMA_ALPHA).clamp(0.01, 1.0) as f64;
+        let render_history = env_usize("GRID_BENCH_RENDER_HISTORY", history);
+        Self {
+            render_fps: FpsCounter::new(render_history, budget_ms, ema_alpha),
+            log_fps: FpsCounter::new(render_history, budget_ms, ema_alpha),
+            frame_fps: FpsCounter::new(
+                env_usize("GRID_BENCH_CALLBACK_HISTORY", history),
+                budget_ms,
+                ema_alpha,
+            ),
+            started_at: Instant::now(),
+            session: SessionSummary::new(
+                logs_window.then(|| output_path("GRID_BENCH_SUMMARY_PATH", "frame_summary.txt")),
+            ),
+            baseline: Baseline::from_env(),
+            target_hz,
+            target_assumed: refresh_hz.is_none(),
+            compact: false,
+            instant_fps: false,
+            precise: false,
+            paused: false,
+            frozen: false,
+            marker_pending: false,
+            batch_append: None,
+            worst_frame: None,
+            worst_frame_file: logs_window.then(|| {
+                WorstFrameFile::new(output_path(
+                    "GRID_BENCH_WORST_FRAME_PATH",
+                    "worst_frame.json",
+                ))
+            }),
+            frame: 0,
+            recent_actions: VecDeque::with_capacity(ACTION_HISTORY),
+            fps_alarm: env_var("GRID_BENCH_FPS_ALARM")
+                .and_then(|v| v.parse().ok())
+                .filter(|_| logs_window),
+            fps_alarm_path: output_path("GRID_BENCH_FPS_ALARM_PATH", "fps_alarm.jsonl"),
+            fps_alarm_raised: false,
+            collapsed_sections: HashSet::new(),
+            hud_ab: env_bool("GRID_BENCH_HUD_AB", false),
+            hud_hidden: false,
+            hud_was_hidden: false,
+            frame_interval: (fps_cap > 0.0).then(|| Duration::from_secs_f32(1.0 / fps_cap)),
+            next_frame_at: Instant::now(),
+            grid_render_time: Duration::ZERO,
+            grid_size: (0, 0, 0.0),
+            cells_built: 0,
+            #[cfg(feature = "fiber")]
+            prev_cells_built: 0,
+            grid_scroll: Point::default(),
+            #[cfg(feature = "rss")]
+            rss: RssSampler::new(),
+            #[cfg(feature = "fiber")]
+            frame_log: logs_window.then(|| FrameLog::from_env().register()),
+            #[cfg(feature = "fiber")]
+            upload_history: VecDeque::with_capacity(UPLOAD_HISTORY + 1),
+            #[cfg(feature = "fiber")]
+            replay_history: VecDeque::with_capacity(REPLAY_HISTORY + 1),
+            #[cfg(feature = "fiber")]
+            hitbox_history: VecDeque::with_capacity(HITBOX_HISTORY + 1),
+            #[cfg(feature = "fiber")]
+            peak_upload_bytes: 0,
+            #[cfg(feature = "fiber")]
+            peak_pool_segments: 0,
+            #[cfg(feature = "fiber")]
+            prev_counts: None,
+            #[cfg(feature = "fiber")]
+            count_deltas: false,
+        }
+    }
+
+    fn schedule_frame_callback(this: Entity<Self>, window: &mut Window) {
+        let this_weak = this.downgrade();
+        window.on_next_frame(move |window, cx| {
+            // The first callback in any window follows the first completed frame.
+            FIRST_FRAME.call_once(|| {
+                if let Some(launched_at) = LAUNCHED_AT.get() {
+                    let elapsed_ms = launched_at.elapsed().as_secs_f64() * 1000.0;
+                    println!("Time to first frame: {:.1} ms", elapsed_ms);
+                }
+            });
+            if let Some(this) = this_weak.upgrade() {
+                this.update(cx, |fps_view, cx| {
+                    // Frames still happen while paused (on hover, say); don't keep them coming.
+                    if !fps_view.paused && fps_view.frame_due() {
+                        if !fps_view.frozen {
+                            fps_view.frame_fps.record();
+                        }
+                        cx.notify();
+                    }
+                });
+                Self::schedule_frame_callback(this, window);
+            }
+        });
+    }
+}
+
+impl Render for FpsView {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !self.paused {
+            // Under a cap, the frame callback paces redraws instead.
+            if self.frame_interval.is_none() {
+                window.request_animation_frame();
+            }
+            self.log_fps.record();
+            self.session.record_frame(self.log_fps.last_frame_ms());
+            if !self.frozen {
+                self.render_fps.record();
+            }
+        }
+        #[cfg(feature = "rss")]
+        self.rss.sample();
+
+        let marker = std::mem::take(&mut self.marker_pending);
+        let elapsed_ms = self.started_at.elapsed().as_secs_f64() * 1000.0;
+        // This render's diagnostics and frame interval describe the previous frame.
+        let hud_shown = !std::mem::replace(&mut self.hud_was_hidden, self.hud_hidden);
+        #[cfg(feature = "fiber")]
+        let frame_cells = std::mem::replace(&mut self.prev_cells_built, self.cells_built);
+
+        #[cfg(feature = "fiber")]
+        let (diagnostics, frame_cost_ms) = {
+            let diag = window.frame_diagnostics();
+            if marker {
+                print_marker(diag.frame_number, elapsed_ms);
+            }
+            let context = FrameContext {
+                elapsed_ms,
+                frame_delta_ms: self.log_fps.last_frame_ms(),
+                render_fps: self.log_fps.fps,
+                grid_render_time: self.grid_render_time,
+                rows: self.grid_size.0,
+                cols: self.grid_size.1,
+                viewport: window.viewport_size(),
+                scroll: self.grid_scroll,
+                marker,
+                hud: hud_shown,
+                #[cfg(feature = "rss")]
+                rss_bytes: self.rss.bytes,
+            };
+            if let Some(frame_log) = &self.frame_log {
+                lock_unpoisoned(frame_log).log(&diag, &context);
+            }
+            self.frame = diag.frame_number;
+            let worst = self.is_worst_frame(context.frame_delta_ms);
+            let alarm = self.fps_alarm_due();
+            if worst || alarm {
+                let fields = frame_fields(&diag, &context)
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value))
+                    .collect();
+                if alarm {
+                    self.record_fps_alarm(&fields);
+                }
+                if worst {
+                    self.record_worst_frame(diag.frame_number, context.frame_delta_ms, fields);
+                }
+            }
+            self.session.record_diagnostics(&diag);
+
+            let upload_bytes = diag.estimated_instance_upload_bytes as u64;
+            self.upload_history.push_back(upload_bytes);
+            if self.upload_history.len() > UPLOAD_HISTORY {
+                self.upload_history.pop_front();
+            }
+            self.peak_upload_bytes = self.peak_upload_bytes.max(upload_bytes);
+
+            if diag.paint_fibers > 0 {
+                self.replay_history
+                    .push_back(diag.paint_replayed_subtrees as f64 / diag.paint_fibers as f64);
+                if self.replay_history.len() > REPLAY_HISTORY {
+                    self.replay_history.pop_front();
+                }
+            }
+            self.hitbox_history.push_back((
+                diag.hitboxes_snapshot_rebuilt as usize,
+                diag.layout_fibers == 0,
+            ));
+            if self.hitbox_history.len() > HITBOX_HISTORY {
+                self.hitbox_history.pop_front();
+            }
+            self.peak_pool_segments = self.peak_pool_segments.max(diag.total_pool_segments);
+
+            let prev_counts = self.prev_counts.replace(frame_counts(&diag));
+            if self.batch_append.is_some() {
+                let frame_ms = self.log_fps.last_frame_ms();
+                self.record_batch_append(BatchSample::from_diagnostics(&diag, frame_ms));
+            }
+
+            // Compact mode still logs every frame; it only skips building the sections.
+            let diagnostics = (!self.compact && !self.hud_hidden)
+                .then(|| self.render_diagnostics(&diag, prev_counts.as_ref(), frame_cells, cx));
+            (diagnostics, diag.total_time.as_secs_f64() * 1000.0)
+        };
+        #[cfg(not(feature = "fiber"))]
+        let (diagnostics, frame_cost_ms): (Option<gpui::Div>, _) = {
+            // Upstream has no frame number, so count the frames this view has recorded.
+            if marker {
+                print_marker(self.session.frames, elapsed_ms);
+            }
+            let frame_ms = self.log_fps.last_frame_ms();
+            self.frame = self.session.frames;
+            let worst = self.is_worst_frame(frame_ms);
+            let alarm = self.fps_alarm_due();
+            if worst || alarm {
+                let fields = serde_json::Map::from_iter([
+                    ("delta_ms".to_string(), serde_json::json!(frame_ms)),
+                    (
+                        "grid_render_us".to_string(),
+                        serde_json::json!(self.grid_render_time.as_micros() as u64),
+                    ),
+                    (
+                        "render_fps".to_string(),
+                        serde_json::json!(self.log_fps.fps),
+                    ),
+                ]);
+                if alarm {
+                    self.record_fps_alarm(&fields);
+                }
+                if worst {
+                    self.record_worst_frame(self.session.frames, frame_ms, fields);
+                }
+            }
+            if self.batch_append.is_some() {
+                self.record_batch_append(BatchSample { frame_ms });
+            }
+            // Without frame timings, the interval is the only cost, and vsync can hide it.
+            (None, frame_ms)
+        };
+
+        if let Some(worst_frame_file) = &mut self.worst_frame_file {
+            worst_frame_file.write_if_due();
+        }
+
+        if self.hud_ab {
+            self.session.record_hud_frame(hud_shown, frame_cost_ms);
+            if self.hud_hidden {
+                return div();
+            }
+        }
+
+        let last_frame_ms = self.render_fps.last_frame_ms();
+        let (shown_fps, fps_label) = if self.instant_fps {
+            let fps = if last_frame_ms > 0.0 {
+                1000.0 / last_frame_ms
+            } else {
+                0.0
+            };
+            (fps, "now")
+        } else {
+            (self.render_fps.fps, "avg")
+        };
+        let fps = div()
+            .flex()
+            .gap_2()
+            .text_color(self.fps_color(shown_fps))
+            .font_weight(gpui::FontWeight::BOLD)
+            .child(self.fps_text(shown_fps, 2))
+            .child(
+                div()
+                    .text_color(rgb(0xaaaaaa))
+                    .font_weight(gpui::FontWeight::NORMAL)
+                    .child(fps_label),
+            )
+            .when(self.paused, |this| {
+                this.child(div().text_color(rgb(0xffcc00)).child("PAUSED"))
+            })
+            .when(self.frozen && !self.paused, |this| {
+                this.child(div().text_color(rgb(0x00aaff)).child("FROZEN"))
+            });
+        if self.compact {
+            return div().text_xs().child(fps);
+        }
+
+        #[cfg(feature = "rss")]
+        let memory = Some(self.section("Memory", cx, |this| {
+            this.child(line("rss (est.)", self.bytes_text(self.rss.bytes)))
+        }));
+        #[cfg(not(feature = "rss"))]
+        let memory: Option<gpui::Div> = None;
+
+        // Fiber builds time the whole frame; upstream can only time the grid's own render.
+        #[cfg(feature = "fiber")]
+        let per_cell = self.per_cell_text(frame_cost_ms, frame_cells);
+        #[cfg(not(feature = "fiber"))]
+        let per_cell = {
+            let render_ms = self.grid_render_time.as_secs_f64() * 1000.0;
+            self.per_cell_text(render_ms, self.cells_built)
+        };
+
+        let percentiles = self.render_fps.percentiles();
+        let low_1 = self.render_fps.low_fps(0.01);
+        let low_01 = self.render_fps.low_fps(0.001);
+        let baseline = self
+            .baseline
+            .as_ref()
+            .map(|baseline| self.render_baseline(baseline, cx));
+
+        div()
+            .flex()
+            .flex_col()
+            .text_xs()
+            .child(fps)
+            .child(line("ema", self.fps_text(self.render_fps.ema_fps(), 2)))
+            .child(line(
+                "frame",
+                frame_time_value(self.ms_text(last_frame_ms), last_frame_ms),
+            ))
+            .child(line(
+                "target",
+                if self.target_assumed {
+                    format!("{:.0} Hz (assumed)", self.target_hz)
+                } else {
+                    format!("{:.0} Hz", self.target_hz)
+                },
+            ))
+            .child(line("1% low", self.low_fps_value(low_1)))
+            .child(line("0.1% low", self.low_fps_value(low_01)))
+            .child(line(
+                "dropped",
+                format!(
+                    "{} / {}, worst {}",
+                    self.render_fps.dropped_frames(),
+                    self.render_fps.interval_count(),
+                    self.ms_text(self.render_fps.worst_frame_ms())
+                ),
+            ))
+            .child(line(
+                "worst",
+                match self.worst_frame {
+                    Some(worst) => {
+                        format!("{} @ frame {}", self.ms_text(worst.frame_ms), worst.frame)
+                    }
+                    None => "-".to_string(),
+                },
+            ))
+            .child(self.section("Frame Time", cx, |this| {
+                this.child(line("p50", self.ms_text(percentiles.p50)))
+                    .child(line("p95", self.ms_text(percentiles.p95)))
+                    .child(line("p99", self.ms_text(percentiles.p99)))
+                    .child(self.sparkline(&self.render_fps.frame_times_ms()))
+                    .child(self.histogram())
+            }))
+            .child(self.section("CPU", cx, |this| {
+                this.child(line(
+                    "render",
+                    self.ms_text(self.grid_render_time.as_secs_f64() * 1000.0),
+                ))
+                .child(line("per cell", per_cell))
+                .child(line("bottleneck", self.bottleneck()))
+                .when_some(self.session.hud_cost_ms(), |this, hud_cost_ms| {
+                    this.child(line("hud cost", format!("{:+.2} ms", hud_cost_ms)))
+                })
+            }))
+            .children(memory)
+            .children(diagnostics)
+            .children(baseline)
+    }
+}
+
+impl FpsView {
+    /// Green within 5% of the refresh rate, yellow above half of it, red below.
+    fn fps_color(&self, fps: f64) -> gpui::Rgba {
+        let target = self.target_hz as f64;
+        if fps >= target * 0.95 {
+            rgb(0x00ff00)
+        } else if fps >= target * 0.5 {
+            rgb(0xffcc00)
+        } else {
+            rgb(0xff4444)
+        }
+    }
+
+    /// The decimal places FPS is shown with: `rounded`, or one more in precise mode.
+    fn fps_decimals(&self, rounded: usize) -> usize {
+        rounded + usize::from(self.precise)
+    }
+
+    fn fps_text(&self, fps: f64, rounded: usize) -> String {
+        format!("{:.*} FPS", self.fps_decimals(rounded), fps)
+    }
+
+    /// `cost_ms` spread over `cells`, the cells the grid built in the frame it describes.
+    fn per_cell_text(&self, cost_ms: f64, cells: usize) -> String {
+        if cells == 0 {
+            return "-".to_string();
+        }
+        format!("{:.3} µs", cost_ms * 1000.0 / cells as f64)
+    }
+
+    /// Milliseconds, or whole microseconds in precise mode.
+    fn ms_text(&self, ms: f64) -> String {
+        if self.precise {
+            format!("{:.0} µs", ms * 1000.0)
+        } else {
+            format!("{:.2} ms", ms)
+        }
+    }
+
+    /// `format_bytes`, or the exact byte count in precise mode.
+    #[cfg(any(feature = "fiber", feature = "rss"))]
+    fn bytes_text(&self, bytes: u64) -> String {
+        if self.precise {
+            format!("{} B", bytes)
+        } else {
+            format_bytes(bytes)
+        }
+    }
+
+    fn low_fps_value(&self, fps: f64) -> impl IntoElement {
+        div()
+            .when(fps < LOW_FPS_THRESHOLD, |this| {
+                this.text_color(rgb(0xff4444))
+            })
+            .child(self.fps_text(fps, 1))
+    }
+
+    /// A HUD section whose header collapses it when clicked. A collapsed section doesn't
+    /// build its lines at all, so it also takes them out of the HUD's element count.
+    fn section(
+        &self,
+        title: &'static str,
+        cx: &Context<Self>,
+        lines: impl FnOnce(gpui::Div) -> gpui::Div,
+    ) -> gpui::Div {
+        let collapsed = self.collapsed_sections.contains(title);
+        div()
+            .flex()
+            .flex_col()
+            .child(
+                div()
+                    .id(title)
+                    .mt_1()
+                    .text_color(rgb(0x888888))
+                    .hover(|style| style.text_color(rgb(0xcccccc)))
+                    .cursor_pointer()
+                    .child(format!("{} {title}", if collapsed { "▸" } else { "▾" }))
+                    .on_click(cx.listener(move |fps_view, _, _, cx| {
+                        if !fps_view.collapsed_sections.remove(title) {
+                            fps_view.collapsed_sections.insert(title);
+                        }
+                        cx.notify();
+                    })),
+            )
+            .when(!collapsed, lines)
+    }
+
+    fn render_baseline(&self, baseline: &Baseline, cx: &Context<Self>) -> gpui::Div {
+        #[cfg(feature = "fiber")]
+        let upload = {
+            let avg_upload_bytes = self.session.avg_upload_bytes();
+            baseline_delta(
+                format!(
+                    "{} / {}",
+                    self.bytes_text(avg_upload_bytes as u64),
+                    self.bytes_text(baseline.avg_upload_bytes as u64)
+                ),
+                avg_upload_bytes,
+                baseline.avg_upload_bytes,
+                false,
+            )
+        };
+        // Upstream has no upload estimate to compare against.
+        #[cfg(not(feature = "fiber"))]
+        let upload = format!("- / {:.0} B", baseline.avg_upload_bytes);
+
+        let avg_fps = self.session.avg_fps();
+
+        self.section("Baseline", cx, |this| {
+            this.child(line(
+                "frames",
+                format!("{} / {}", self.session.frames, baseline.frames),
+            ))
+            .child(line(
+                "avg fps",
+                baseline_delta(
+                    format!(
+                        "{:.*} / {:.*}",
+                        self.fps_decimals(1),
+                        avg_fps,
+                        self.fps_decimals(1),
+                        baseline.avg_fps
+                    ),
+                    avg_fps,
+                    baseline.avg_fps,
+                    true,
+                ),
+            ))
+            .child(line("avg upload", upload))
+        })
+    }
+
+    /// One bar per frame time; full height is twice the frame budget.
+    fn sparkline(&self, samples: &[f64]) -> gpui::Div {
+        let budget_ms = self.render_fps.budget_ms;
+
+        bar_graph(samples.iter().map(|&ms| {
+            let color = if ms <= budget_ms {
+                rgb(0x00ff00)
+            } else if ms <= budget_ms * 2.0 {
+                rgb(0xffcc00)
+            } else {
+                rgb(0xff4444)
+            };
+            ((ms / (budget_ms * 2.0)) as f32, color)
+        }))
+    }
+
+    /// Frame-time distribution over `0..HISTOGRAM_MAX_MS`, scaled to the fullest bucket
+    /// and colored by where the bucket falls against the frame budget.
+    fn histogram(&self) -> gpui::Div {
+        let budget_ms = self.render_fps.budget_ms;
+        let counts = self
+            .render_fps
+            .histogram(HISTOGRAM_BUCKETS, HISTOGRAM_MAX_MS);
+        let max_count = counts.iter().copied().fold(1, usize::max);
+        let bucket_ms = HISTOGRAM_MAX_MS / counts.len() as f64;
+
+        bar_graph(counts.iter().enumerate().map(|(bucket, &count)| {
+            let start_ms = bucket as f64 * bucket_ms;
+            let color = if start_ms < budget_ms {
+                rgb(0x00ff00)
+            } else if start_ms < budget_ms * 2.0 {
+                rgb(0xffcc00)
+            } else {
+                rgb(0xff4444)
+            };
+            (count as f32 / max_count as f32, color)
+        }))
+    }
+
+    /// Upload sizes scaled to the largest in the window, colored by absolute size so
+    /// full re-uploads stand out from incremental ones.
+    #[cfg(feature = "fiber")]
+    fn upload_graph(&self) -> gpui::Div {
+        let max_bytes = self.upload_history.iter().copied().fold(1, u64::max);
+
+        bar_graph(self.upload_history.iter().map(|&bytes| {
+            let color = if bytes < UPLOAD_WARN_BYTES {
+                rgb(0x00aaff)
+            } else if bytes < UPLOAD_ALERT_BYTES {
+                rgb(0xffcc00)
+            } else {
+                rgb(0xff4444)
+            };
+            (bytes as f32 / max_bytes as f32, color)
+        }))
+    }
+
+    /// A count as the Counts section shows it: its value, or the change from `prev`.
+    #[cfg(feature = "fiber")]
+    fn count_value(&self, value: i64, prev: Option<i64>) -> String {
+        match (self.count_deltas, prev) {
+            (false, _) => value.to_string(),
+            (true, Some(prev)) => format!("{:+}", value - prev),
+            (true, None) => "-".to_string(),
+        }
+    }
+
+    #[cfg(feature = "fiber")]
+    fn render_diagnostics(
+        &self,
+        diag: &gpui::FrameDiagnostics,
+        prev_counts: Option<&FrameCounts>,
+        cells_built: usize,
+        cx: &Context<Self>,
+    ) -> gpui::Div {
+        let upload_bytes = diag.estimated_instance_upload_bytes as u64;
+        let upload_per_cell = upload_bytes as f64 / cells_built.max(1) as f64;
+
+        let replay_ratio = self.replay_history.back().copied().unwrap_or(0.0);
+
+        div()
+            .flex()
+            .flex_col()
+            .child(self.section("Fibers", cx, |this| {
+                this.child(line(
+                    "paint",
+                    div()
+                        .when(self.missed_replay(), |this| this.text_color(rgb(0xff4444)))
+                        .child(format!(
+                            "{} ({:.0}% replayed)",
+                            diag.paint_fibers,
+                            replay_ratio * 100.0
+                        )),
+                ))
+                .child(line(
+                    "hitboxes rebuilt",
+                    div()
+                        .when(
+                            self.static_hitbox_rebuild_ratio() > HITBOX_STATIC_ALERT_RATIO,
+                            |this| this.text_color(rgb(0xff4444)),
+                        )
+                        .child(format!(
+                            "{:.0}% of {} frames",
+                            self.hitbox_rebuild_ratio() * 100.0,
+                            self.hitbox_history.len()
+                        )),
+                ))
+            }))
+            .child(self.section("Counts", cx, |this| {
+                let counts = frame_counts(diag);
+                this.child(line(
+                    "shown",
+                    if self.count_deltas {
+                        "change from last frame"
+                    } else {
+                        "this frame"
+                    },
+                ))
+                .children((0..counts.len()).map(|index| {
+                    let (label, value) = counts[index];
+                    let prev = prev_counts.map(|prev_counts| prev_counts[index].1);
+                    line(label, self.count_value(value, prev))
+                }))
+            }))
+            .child(self.section("GPU", cx, |this| {
+                this.child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_2()
+                        .child(line("upload", self.bytes_text(upload_bytes)).flex_1())
+                        .child(self.upload_graph()),
+                )
+                .child(line("peak upload", self.bytes_text(self.peak_upload_bytes)))
+                .child(line("upload / cell", format!("{:.1} B", upload_per_cell)))
+            }))
+            .child(self.section("Pool", cx, |this| {
+                this.child(line("segments", diag.total_pool_segments.to_string()))
+                    .child(line("peak segments", self.peak_pool_segments.to_string()))
+            }))
+    }
+
+    /// A rough guess at what holds FPS below the target, from how much of the average
+    /// frame interval the grid's render takes on the CPU.
+    fn bottleneck(&self) -> &'static str {
+        let fps = self.render_fps.fps;
+        if fps <= 0.0 {
+            return "-";
+        }
+        if fps >= self.target_hz as f64 * 0.95 {
+            return "none";
+        }
+
+        let cpu_share = self.grid_render_time.as_secs_f64() * fps;
+        if cpu_share >= CPU_BOUND_CPU_SHARE {
+            "CPU-bound"
+        } else if cpu_share < GPU_BOUND_CPU_SHARE {
+            "GPU-bound"
+        } else {
+            "unclear"
+        }
+    }
+
+    /// Whether the frame callback should emit a frame now. Always true without a cap;
+    /// with one, frames keep to the cap's cadence, and a late frame restarts it.
+    fn frame_due(&mut self) -> bool {
+        let Some(interval) = self.frame_interval else {
+            return true;
+        };
+        let now = Instant::now();
+        // Half a refresh of slack, so vsync jitter doesn't push a frame a whole refresh late.
+        let slack = Duration::from_secs_f32(0.5 / self.target_hz);
+        if now + slack < self.next_frame_at {
+            return false;
+        }
+        self.next_frame_at = (self.next_frame_at + interval).max(now);
+        true
+    }
+
+    fn is_worst_frame(&self, frame_ms: f64) -> bool {
+        self.session.frames > WORST_FRAME_WARMUP_FRAMES
+            && frame_ms > self.worst_frame.map_or(0.0, |worst| worst.frame_ms)
+    }
+
+    /// Takes a new worst frame and hands it to `worst_frame_file`, with the grid as it
+    /// stood and `fields`, the frame's diagnostics.
+    fn record_worst_frame(
+        &mut self,
+        frame: u64,
+        frame_ms: f64,
+        fields: serde_json::Map<String, serde_json::Value>,
+    ) {
+        self.worst_frame = Some(WorstFrame { frame, frame_ms });
+
+        let (rows, cols, cell_size) = self.grid_size;
+        let report = serde_json::json!({
+            "frame": frame,
+            "frame_ms": frame_ms,
+            "grid": {
+                "rows": rows,
+                "cols": cols,
+                "cell_size": cell_size,
+                "scroll_x": f32::from(self.grid_scroll.x),
+                "scroll_y": f32::from(self.grid_scroll.y),
+            },
+            "diagnostics": fields,
+        });
+        if let Some(worst_frame_file) = &mut self.worst_frame_file {
+            worst_frame_file.report = Some(report);
+        }
+    }
+
+    /// Keeps `action` among the recent actions, stamped with the latest frame.
+    fn record_action(&mut self, action: String) {
+        if self.recent_actions.len() == ACTION_HISTORY {
+            self.recent_actions.pop_front();
+        }
+        self.recent_actions.push_back((self.frame, action));
+    }
+
+    /// Whether the render FPS has just dropped below `GRID_BENCH_FPS_ALARM`. Fires once
+    /// per drop, and not while paused.
+    fn fps_alarm_due(&mut self) -> bool {
+        let Some(threshold) = self.fps_alarm else {
+            return false;
+        };
+        if self.paused || self.session.frames <= FPS_ALARM_WARMUP_FRAMES {
+            return false;
+        }
+        let below = self.log_fps.fps < threshold;
+        let due = below && !self.fps_alarm_raised;
+        self.fps_alarm_raised = below;
+        due
+    }
+
+    /// Appends the recent actions and `fields`, the frame's diagnostics, to
+    /// `fps_alarm_path` as one JSON line.
+    fn record_fps_alarm(&self, fields: &serde_json::Map<String, serde_json::Value>) {
+        let fps = self.log_fps.fps;
+        log::warn!("FPS fell to {fps:.1} at frame {}", self.frame);
+        let actions = self
+            .recent_actions
+            .iter()
+            .map(|(frame, action)| serde_json::json!({ "frame": frame, "action": action }))
+            .collect::<Vec<_>>();
+        let report = serde_json::json!({
+            "frame": self.frame,
+            "render_fps": fps,
+            "threshold": self.fps_alarm,
+            "actions": actions,
+            "diagnostics": fields,
+        });
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.fps_alarm_path)
+            .and_then(|mut file| writeln!(file, "{report}"));
+        if let Err(err) = result {
+            log::error!("Failed to write {}: {err}", self.fps_alarm_path);
+        }
+    }
+
+    /// Steps a batch append with the frame this render describes: first the steady frame
+    /// before the append, then the frame that built the new rows, printing both.
+    fn record_batch_append(&mut self, sample: BatchSample) {
+        let Some(batch_append) = self.batch_append.as_mut() else {
+            return;
+        };
+        let Some(before) = batch_append.before.take() else {
+            batch_append.before = Some(sample);
+            return;
+        };
+        let (from, to) = batch_append.rows;
+        println!("Batch append: {from} -> {to} rows");
+        println!("  before: {}", before.describe());
+        println!("  after:  {}", sample.describe());
+        self.batch_append = None;
+    }
+
+    /// Called by the grid before it builds a frame: under `GRID_BENCH_HUD_AB`, flips
+    /// between frames with and without the overlay. Returns whether this one leaves it out.
+    fn next_hud_frame(&mut self) -> bool {
+        if self.hud_ab {
+            self.hud_hidden = !self.hud_hidden;
+        }
+        self.hud_hidden
+    }
+
+    /// Resuming starts the FPS windows afresh so the pause isn't counted as a dropped frame.
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        if !self.paused {
+            self.render_fps.clear();
+            self.log_fps.clear();
+            self.frame_fps.clear();
+        }
+    }
+
+    /// Like resuming from a pause, thawing starts the FPS windows afresh.
+    fn toggle_frozen(&mut self) {
+        self.frozen = !self.frozen;
+        if !self.frozen {
+            self.render_fps.clear();
+            self.frame_fps.clear();
+        }
+    }
+
+    /// Share of recent frames that rebuilt the hitbox snapshot.
+    #[cfg(feature = "fiber")]
+    fn hitbox_rebuild_ratio(&self) -> f64 {
+        let rebuilt = self.hitbox_history.iter().filter(|(count, _)| *count > 0);
+        rebuilt.count() as f64 / self.hitbox_history.len().max(1) as f64
+    }
+
+    /// Share of recent static frames that still rebuilt the hitbox snapshot; nothing moved,
+    /// so the old snapshot should have been reusable.
+    #[cfg(feature = "fiber")]
+    fn static_hitbox_rebuild_ratio(&self) -> f64 {
+        let static_frames = self
+            .hitbox_history
+            .iter()
+            .filter(|(_, is_static)| *is_static);
+        let (frames, rebuilt) = static_frames.fold((0, 0), |(frames, rebuilt), (count, _)| {
+            (frames + 1, rebuilt + usize::from(*count > 0))
+        });
+        rebuilt as f64 / frames.max(1) as f64
+    }
+
+    /// Replay was working for most of the history and then stopped for the last few
+    /// frames. A single unreplayed frame (the grid changing) doesn't count, and neither
+    /// does a run that never replayed (animation).
+    #[cfg(feature = "fiber")]
+    fn missed_replay(&self) -> bool {
+        if self.replay_history.len() < REPLAY_HISTORY {
+            return false;
+        }
+
+        let split = self.replay_history.len() - REPLAY_DROP_FRAMES;
+        let before = self.replay_history.iter().take(split).sum::<f64>() / split as f64;
+        before >= REPLAY_HEALTHY_RATIO
+            && self
+                .replay_history
+                .iter()
+                .skip(split)
+                .all(|&ratio| ratio < REPLAY_MISSED_RATIO)
+    }
+
+    #[cfg(feature = "fiber")]
+    fn reset_peaks(&mut self) {
+        self.peak_upload_bytes = 0;
+        self.peak_pool_segments = 0;
+    }
+}
+
+/// A fixed-size strip of bars; each item is a height fraction (clamped to 1) and a color.
+fn bar_graph(bars: impl ExactSizeIterator<Item = (f32, gpui::Rgba)>) -> gpui::Div {
+    let bar_width = SPARKLINE_WIDTH / bars.len().max(1) as f32;
+
+    div()
+        .flex()
+        .items_end()
+        .w(px(SPARKLINE_WIDTH))
+        .h(px(SPARKLINE_HEIGHT))
+        .bg(rgb(0x222222))
+        .children(bars.map(move |(fraction, color)| {
+            div()
+                .w(px(bar_width))
+                .h(px(SPARKLINE_HEIGHT * fraction.min(1.0)))
+                .bg(color)
+        }))
+}
+
+/// Announces a marked frame on stdout, with wall-clock time to line it up with captures
+/// from other tools.
+fn print_marker(frame: u64, elapsed_ms: f64) {
+    let unix_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    println!("Marker: frame {frame}, {elapsed_ms:.1} ms since start, unix time {unix_ms} ms");
+}
+
+/// The longest frame interval of the run so far, past `WORST_FRAME_WARMUP_FRAMES`.
+#[derive(Clone, Copy)]
+struct WorstFrame {
+    frame: u64,
+    frame_ms: f64,
+}
+
+/// `GRID_BENCH_WORST_FRAME_PATH`. Holds the latest worst frame's report and writes it at
+/// most once per `WORST_FRAME_WRITE_INTERVAL`, then on quit or when dropped, so beating
+/// the record mid-run doesn't write a file from inside a frame.
+struct WorstFrameFile {
+    path: String,
+    /// The report not yet written.
+    report: Option<serde_json::Value>,
+    written_at: Option<Instant>,
+}
+
+impl WorstFrameFile {
+    fn new(path: String) -> Self {
+        Self {
+            path,
+            report: None,
+            written_at: None,
+        }
+    }
+
+    fn write_if_due(&mut self) {
+        if self
+            .written_at
+            .is_some_and(|written_at| written_at.elapsed() < WORST_FRAME_WRITE_INTERVAL)
+        {
+            return;
+        }
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        let Some(report) = self.report.take() else {
+            return;
+        };
+        self.written_at = Some(Instant::now());
+        let result = serde_json::to_string_pretty(&report)
+            .map_err(std::io::Error::other)
+            .and_then(|json| std::fs::write(&self.path, json + "\n"));
+        if let Err(err) = result {
+            log::error!("Failed to write {}: {err}", self.path);
+        }
+    }
+}
+
+impl Drop for WorstFrameFile {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// A batch append (`b`) in progress: the row counts before and after, and the frame
+/// before the append once it has been seen.
+struct BatchAppend {
+    rows: (usize, usize),
+    before: Option<BatchSample>,
+}
+
+/// What the batch append report shows of one frame.
+struct BatchSample {
+    /// Interval since the frame before.
+    frame_ms: f64,
+    #[cfg(feature = "fiber")]
+    frame: u64,
+    #[cfg(feature = "fiber")]
+    total_ms: f64,
+    #[cfg(feature = "fiber")]
+    upload_bytes: u64,
+    #[cfg(feature = "fiber")]
+    segments: usize,
+    #[cfg(feature = "fiber")]
+    quads: usize,
+}
+
+impl BatchSample {
+    #[cfg(feature = "fiber")]
+    fn from_diagnostics(diag: &gpui::FrameDiagnostics, frame_ms: f64) -> Self {
+        Self {
+            frame_ms,
+            frame: diag.frame_number,
+            total_ms: diag.total_time.as_secs_f64() * 1000.0,
+            upload_bytes: diag.estimated_instance_upload_bytes as u64,
+            segments: diag.total_pool_segments,
+            quads: diag.quads,
+        }
+    }
+
+    #[cfg(feature = "fiber")]
+    fn describe(&self) -> String {
+        format!(
+            "frame {}: {:.2} ms ({:.2} ms interval), upload {}, {} segments, {} quads",
+            self.frame,
+            self.total_ms,
+            self.frame_ms,
+            format_bytes(self.upload_bytes),
+            self.segments,
+            self.quads
+        )
+    }
+
+    #[cfg(not(feature = "fiber"))]
+    fn describe(&self) -> String {
+        format!("{:.2} ms interval", self.frame_ms)
+    }
+}
+
+/// The counts the HUD's Counts section lists for a frame.
+#[cfg(feature = "fiber")]
+type FrameCounts = [(&'static str, i64); 9];
+
+#[cfg(feature = "fiber")]
+fn frame_counts(diag: &gpui::FrameDiagnostics) -> FrameCounts {
+    [
+        ("layout fibers", diag.layout_fibers as i64),
+        ("prepaint fibers", diag.prepaint_fibers as i64),
+        ("paint fibers", diag.paint_fibers as i64),
+        ("quads", diag.quads as i64),
+        ("mono sprites", diag.monochrome_sprites as i64),
+        ("poly sprites", diag.polychrome_sprites as i64),
+        ("hitboxes", diag.hitboxes_in_snapshot as i64),
+        ("segments", diag.total_pool_segments as i64),
+        ("mutated segments", diag.mutated_pool_segments as i64),
+    ]
+}
+
+#[cfg(any(feature = "fiber", feature = "rss"))]
+fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// The frame time, formatted as `text`, on a green, yellow or red background, readable
+/// at a glance.
+fn frame_time_value(text: String, frame_ms: f64) -> impl IntoElement {
+    let background = if frame_ms < FRAME_TIME_WARN_MS {
+        rgb(0x00aa44)
+    } else if frame_ms <= FRAME_TIME_ALERT_MS {
+        rgb(0xccaa00)
+    } else {
+        rgb(0xcc2222)
+    };
+    div()
+        .px_1()
+        .rounded_sm()
+        .bg(background)
+        .text_color(gpui::black())
+        .child(text)
+}
+
+/// `value` followed by the percentage change from `baseline`: green when it moved the
+/// right way, red when it regressed, grey within 1%.
+fn baseline_delta(value: String, current: f64, baseline: f64, higher_is_better: bool) -> gpui::Div {
+    let change = if baseline > 0.0 {
+        (current - baseline) / baseline * 100.0
+    } else {
+        0.0
+    };
+    let color = if change.abs() < 1.0 {
+        rgb(0xaaaaaa)
+    } else if (change > 0.0) == higher_is_better {
+        rgb(0x00ff00)
+    } else {
+        rgb(0xff4444)
+    };
+
+    div()
+        .flex()
+        .gap_1()
+        .child(value)
+        .child(div().text_color(color).child(format!("{change:+.1}%")))
+}
+
+fn line(label: &'static str, value: impl IntoElement) -> gpui::Div {
+    div()
+        .flex()
+        .justify_between()
+        .gap_2()
+        .child(div().text_color(rgb(0xaaaaaa)).child(label))
+        .child(div().text_color(gpui::white()).child(value))
+}
+
+/// `GRID_BENCH_SWEEP=start,end,step,frames_per_step`
+#[derive(Clone, Copy)]
+struct SweepConfig {
+    start: usize,
+    end: usize,
+    step: usize,
+    frames_per_step: usize,
+}
+
+impl SweepConfig {
+    fn from_env() -> Option<Self> {
+        let value = env_var("GRID_BENCH_SWEEP")?;
+        let parts = value
+            .split(',')
+            .map(|part| part.trim().parse::<usize>().ok())
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        let [start, end, step, frames_per_step] = parts[..] else {
+            log::warn!("GRID_BENCH_SWEEP expects start,end,step,frames_per_step: {value:?}");
+            return None;
+        };
+
+        Some(Self {
+            start: start.max(1),
+            end: end.max(1),
+            step: step.max(1),
+            frames_per_step: frames_per_step.max(1),
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CellSizeMode {
+    Uniform,
+    /// Each cell gets a deterministic size from `VARIED_CELL_SCALES` to exercise
+    /// non-uniform flex layout.
+    Varied,
+}
+
+impl CellSizeMode {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_CELL_SIZE_MODE").as_deref() {
+            Some("varied") => Self::Varied,
+            _ => Self::Uniform,
+        }
+    }
+
+    fn cell_size(self, base: f32, cell_num: usize) -> f32 {
+        match self {
+            Self::Uniform => base,
+            Self::Varied => {
+                // Multiplicative hash so neighbouring rows don't line up into columns.
+                let index = (cell_num as u64).wrapping_mul(2_654_435_761) >> 16;
+                base * VARIED_CELL_SCALES[index as usize % VARIED_CELL_SCALES.len()]
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ColorMode {
+    /// Hue follows the cell index across the grid.
+    Gradient,
+    /// Every cell gets an unrelated hue derived from the seed, so neighbouring cells
+    /// don't form runs of similar colors.
+    Random { seed: u64 },
+}
+
+impl ColorMode {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_COLOR_MODE").as_deref() {
+            Some("random") => Self::Random {
+                seed: env_var("GRID_BENCH_SEED")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_SEED),
+            },
+            _ => Self::Gradient,
+        }
+    }
+
+    fn hue(self, cell_num: usize, total_cells: usize) -> u32 {
+        match self {
+            Self::Gradient => (cell_num as f32 / total_cells.max(1) as f32 * 360.0) as u32,
+            Self::Random { seed } => {
+                // Spread the index over all bits first; xorshift alone barely changes
+                // the output for neighbouring inputs.
+                let state = seed ^ (cell_num as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                (xorshift64(xorshift64(state)) % 360) as u32
+            }
+        }
+    }
+}
+
+/// What `ColorMode` spreads its hues over (`GRID_BENCH_HUE`).
+#[derive(Clone, Copy, PartialEq)]
+enum HueBasis {
+    /// The cell's number in `GRID_BENCH_ORDER`.
+    Index,
+    Row,
+    Column,
+    /// Whole-cell distance from the center of the grid, so hues form concentric rings.
+    Radial,
+}
+
+impl HueBasis {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_HUE").as_deref() {
+            Some("row") => Self::Row,
+            Some("col") => Self::Column,
+            Some("radial") => Self::Radial,
+            _ => Self::Index,
+        }
+    }
+
+    /// The cell's position along the basis and the number of positions.
+    fn position(
+        self,
+        row: usize,
+        col: usize,
+        order_num: usize,
+        row_count: usize,
+        col_count: usize,
+    ) -> (usize, usize) {
+        match self {
+            Self::Index => (order_num, row_count * col_count),
+            Self::Row => (row, row_count),
+            Self::Column => (col, col_count),
+            Self::Radial => {
+                let center_row = row_count.saturating_sub(1) as f32 / 2.0;
+                let center_col = col_count.saturating_sub(1) as f32 / 2.0;
+                let distance = (row as f32 - center_row).hypot(col as f32 - center_col);
+                let max_distance = center_row.hypot(center_col);
+                (distance.round() as usize, max_distance.round() as usize + 1)
+            }
+        }
+    }
+}
+
+/// A static arrangement of a couple of hues in place of `ColorMode`'s per-cell hues
+/// (`GRID_BENCH_PATTERN`), so runs of cells share a color.
+#[derive(Clone, Copy, PartialEq)]
+enum CellPattern {
+    /// `ColorMode` picks each cell's hue.
+    Gradient,
+    /// Every cell the same color.
+    Solid,
+    /// Rows alternate between two colors.
+    Stripes,
+    /// Cells alternate between two colors along rows and columns.
+    Checker,
+}
+
+impl CellPattern {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_PATTERN").as_deref() {
+            Some("solid") => Self::Solid,
+            Some("stripes") => Self::Stripes,
+            Some("checker") => Self::Checker,
+            _ => Self::Gradient,
+        }
+    }
+
+    fn hue(self, row: usize, col: usize, mode_hue: u32) -> u32 {
+        match self {
+            Self::Gradient => mode_hue,
+            Self::Solid => PATTERN_HUES[0],
+            Self::Stripes => PATTERN_HUES[row % 2],
+            Self::Checker => PATTERN_HUES[(row + col) % 2],
+        }
+    }
+}
+
+/// How cells are numbered for their label and hue. Selection and element ids always
+/// use the row-major index.
+#[derive(Clone, Copy, PartialEq)]
+enum CellOrder {
+    RowMajor,
+    ColumnMajor,
+    /// Clockwise from the top-left corner, winding inwards.
+    Spiral,
+}
+
+impl CellOrder {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_ORDER").as_deref() {
+            Some("column") => Self::ColumnMajor,
+            Some("spiral") => Self::Spiral,
+            _ => Self::RowMajor,
+        }
+    }
+
+    fn index(self, row: usize, col: usize, rows: usize, cols: usize) -> usize {
+        match self {
+            Self::RowMajor => row * cols + col,
+            Self::ColumnMajor => col * rows + row,
+            Self::Spiral => {
+                // Cells in the rings outside this one come first.
+                let ring = row.min(col).min(rows - 1 - row).min(cols - 1 - col);
+                let (height, width) = (rows - 2 * ring, cols - 2 * ring);
+                let outer = rows * cols - height * width;
+                let (i, j) = (row - ring, col - ring);
+
+                outer
+                    + if i == 0 {
+                        j
+                    } else if j == width - 1 {
+                        (width - 1) + i
+                    } else if i == height - 1 {
+                        (width - 1) + (height - 1) + (width - 1 - j)
+                    } else {
+                        2 * (width - 1) + (height - 1) + (height - 1 - i)
+                    }
+            }
+        }
+    }
+}
+
+/// How the grid's cells are built.
+#[derive(Clone, Copy, PartialEq)]
+enum GridLayout {
+    Flat,
+    /// Every cell holds a `NESTED_GRID_SIZE` square grid of its own (`nested_grids`). Only
+    /// the first block animates, so the others are identical from frame to frame and can
+    /// be replayed.
+    NestedGrids,
+    /// `Flat` cells placed at computed coordinates in one sized container, with no row
+    /// elements and no flex between cells (`absolute`). Paints what `Flat` paints, so
+    /// comparing `prepaint_fibers` and frame times against it separates out flex layout.
+    /// Cells are placed on the nominal pitch, so varied cell sizes leave uneven gaps.
+    Absolute,
+}
+
+impl GridLayout {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_LAYOUT").as_deref() {
+            Some("nested_grids") => Self::NestedGrids,
+            Some("absolute") => Self::Absolute,
+            _ => Self::Flat,
+        }
+    }
+
+    fn anim_phase(self, cell_num: usize, anim_phase: u32) -> u32 {
+        match self {
+            Self::NestedGrids if cell_num != 0 => 0,
+            _ => anim_phase,
+        }
+    }
+}
+
+/// Which axes the scroll container scrolls along (`GRID_BENCH_SCROLL_AXIS`). Content past
+/// the viewport along the other axis is clipped.
+#[derive(Clone, Copy, PartialEq)]
+enum ScrollAxis {
+    /// Horizontal only (`x`).
+    X,
+    /// Vertical only (`y`), the usual data grid, and the only axis virtualization windows.
+    Y,
+    Both,
+}
+
+impl ScrollAxis {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_SCROLL_AXIS").as_deref() {
+            Some("x") => Self::X,
+            Some("y") => Self::Y,
+            _ => Self::Both,
+        }
+    }
+
+    fn apply(self, container: gpui::Stateful<gpui::Div>) -> gpui::Stateful<gpui::Div> {
+        match self {
+            Self::X => container.overflow_x_scroll(),
+            Self::Y => container.overflow_y_scroll(),
+            Self::Both => container.overflow_scroll(),
+        }
+    }
+}
+
+/// Which rows follow the animation (`GRID_BENCH_UPDATE_PROFILE`).
+#[derive(Clone, Copy, PartialEq)]
+enum UpdateProfile {
+    /// Every row.
+    Uniform,
+    /// Even rows only; odd rows stay static (`mixed`), like a dashboard where some rows
+    /// update every frame and the rest rarely do.
+    Mixed,
+}
+
+impl UpdateProfile {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_UPDATE_PROFILE").as_deref() {
+            Some("mixed") => Self::Mixed,
+            _ => Self::Uniform,
+        }
+    }
+
+    fn anim_phase(self, row: usize, anim_phase: u32) -> u32 {
+        match self {
+            Self::Mixed if row % 2 == 1 => 0,
+            _ => anim_phase,
+        }
+    }
+}
+
+/// How cell colors are built from their hue (`GRID_BENCH_COLORSPACE`).
+#[derive(Clone, Copy, PartialEq)]
+enum ColorSpace {
+    /// HSL straight into GPUI's `Hsla`, which is sRGB-encoded.
+    Srgb,
+    /// The RGB from the HSL math taken as linear light and encoded to sRGB (`linear`),
+    /// as if the math had been done in linear space. The same settings come out lighter.
+    Linear,
+}
+
+impl ColorSpace {
+    fn from_env() -> Self {
+        match env_var("GRID_BENCH_COLORSPACE").as_deref() {
+            Some("linear") => Self::Linear,
+            _ => Self::Srgb,
+        }
+    }
+
+    fn color(self, h: u32, s: u32, v: u32) -> gpui::Hsla {
+        let color = hsv_to_rgb(h, s, v);
+        match self {
+            Self::Srgb => color,
+            Self::Linear => {
+                let linear = gpui::Rgba::from(color);
+                gpui::Rgba {
+                    r: linear_to_srgb(linear.r),
+                    g: linear_to_srgb(linear.g),
+                    b: linear_to_srgb(linear.b),
+                    a: linear.a,
+                }
+                .into()
+            }
+        }
+    }
+
+    /// For the overlay: the setting and the path it takes.
+    fn description(self) -> &'static str {
+        match self {
+            Self::Srgb => "Color: srgb (Hsla as given)",
+            Self::Linear => "Color: linear (HSL as linear light, encoded to sRGB)",
+        }
+    }
+}
+
+/// The sRGB transfer function.
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// A `NESTED_GRID_SIZE` square grid of cells filling a block `block_size` wide, shading
+/// outwards from `hue`.
+fn inner_grid(hue: u32, block_size: f32, color_space: ColorSpace) -> gpui::AnyElement {
+    let gaps = NESTED_GRID_GAP * (NESTED_GRID_SIZE - 1) as f32;
+    let inner_size = ((block_size - gaps) / NESTED_GRID_SIZE as f32).max(1.0);
+    div()
+        .flex()
+        .flex_col()
+        .gap(px(NESTED_GRID_GAP))
+        .children((0..NESTED_GRID_SIZE).map(|row| {
+            div()
+                .flex()
+                .gap(px(NESTED_GRID_GAP))
+                .children((0..NESTED_GRID_SIZE).map(move |col| {
+                    let hue = (hue + ((row + col) * 8) as u32) % 360;
+                    div()
+                        .size(px(inner_size))
+                        .flex_shrink_0()
+                        .bg(color_space.color(hue, 70, 80))
+                }))
+        }))
+        .into_any_element()
+}
+
+/// One xorshift64 step. Zero maps to itself, so it is replaced with a fixed constant.
+fn xorshift64(state: u64) -> u64 {
+    let mut x = if state == 0 { DEFAULT_SEED } else { state };
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    x
+}
+
+struct SweepStep {
+    rows: usize,
+    cols: usize,
+    avg_fps: f64,
+    p99_ms: f64,
+    upload_bytes: u64,
+}
+
+/// Steps `row_count` from `start` to `end`, holding each value for `frames_per_step`
+/// frames. The frame that applies a new row count is not measured.
+struct SweepRunner {
+    config: SweepConfig,
+    rows: usize,
+    last_frame: Option<Instant>,
+    frame_times_ms: Vec<f64>,
+    upload_bytes: u64,
+    results: Vec<SweepStep>,
+    /// `GRID_BENCH_SWEEP_OUTPUT`
+    output_path: String,
+}
+
+impl SweepRunner {
+    fn new(config: SweepConfig) -> Self {
+        Self {
+            config,
+            rows: config.start,
+            last_frame: None,
+            frame_times_ms: Vec::with_capacity(config.frames_per_step),
+            upload_bytes: 0,
+            results: Vec::new(),
+            output_path: output_path("GRID_BENCH_SWEEP_OUTPUT", "sweep_summary.csv"),
+        }
+    }
+
+    /// Returns the row count to render next, or `None` once every step is recorded.
+    fn record_frame(&mut self, col_count: usize, upload_bytes: u64) -> Option<usize> {
+        let now = Instant::now();
+        if let Some(last) = self.last_frame.replace(now) {
+            self.frame_times_ms
+                .push(now.duration_since(last).as_secs_f64() * 1000.0);
+            self.upload_bytes += upload_bytes;
+        }
+
+        if self.frame_times_ms.len() < self.config.frames_per_step {
+            return Some(self.rows);
+        }
+
+        let frames = self.frame_times_ms.len();
+        let mean_ms = self.frame_times_ms.iter().sum::<f64>() / frames as f64;
+        self.frame_times_ms.sort_by(f64::total_cmp);
+        self.results.push(SweepStep {
+            rows: self.rows,
+            cols: col_count,
+            avg_fps: 1000.0 / mean_ms,
+            p99_ms: percentile(&self.frame_times_ms, 0.99),
+            upload_bytes: self.upload_bytes / frames as u64,
+        });
+        log::info!(
+            "Sweep step: {} rows x {} cols, {:.1} FPS",
+            self.rows,
+            col_count,
+            1000.0 / mean_ms
+        );
+
+        self.frame_times_ms.clear();
+        self.upload_bytes = 0;
+        self.last_frame = None;
+
+        let SweepConfig { end, step, .. } = self.config;
+        self.rows = if self.config.start <= end {
+            self.rows.checked_add(step).filter(|&rows| rows <= end)?
+        } else {
+            self.rows.checked_sub(step).filter(|&rows| rows >= end)?
+        };
+        Some(self.rows)
+    }
+
+    fn write_summary(&self, path: &str) -> std::io::Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        writeln!(file, "rows,cols,avg_fps,p99_ms,upload_bytes")?;
+        for step in &self.results {
+            writeln!(
+                file,
+                "{},{},{:.2},{:.3},{}",
+                step.rows, step.cols, step.avg_fps, step.p99_ms, step.upload_bytes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds `step_size` rows every `interval` frames until the render FPS drops below
+/// `min_fps` (`GRID_BENCH_RAMP`). The FPS is a rolling average, so it trails the grid by
+/// up to a history's worth of frames; a longer interval lets it catch up between steps.
+struct StressRamp {
+    interval: usize,
+    min_fps: f64,
+    frame: usize,
+}
+
+impl StressRamp {
+    fn from_env(target_hz: f32) -> Option<Self> {
+        if !env_bool("GRID_BENCH_RAMP", false) {
+            return None;
+        }
+
+        Some(Self {
+            interval: env_usize("GRID_BENCH_RAMP_FRAMES", DEFAULT_RAMP_FRAMES).max(1),
+            min_fps: env_f32("GRID_BENCH_RAMP_MIN_FPS", target_hz * RAMP_TARGET_FRACTION) as f64,
+            frame: 0,
+        })
+    }
+
+    /// Whether to grow the grid this frame, or `None` once `fps` has dropped below
+    /// `min_fps`.
+    fn advance(&mut self, fps: f64) -> Option<bool> {
+        self.frame += 1;
+        if self.frame <= RAMP_WARMUP_FRAMES {
+            return Some(false);
+        }
+        if fps < self.min_fps {
+            return None;
+        }
+        Some((self.frame - RAMP_WARMUP_FRAMES).is_multiple_of(self.interval))
+    }
+}
+
+/// Resizes the grid to hold the frame time at `target_ms` (`GRID_BENCH_ADAPTIVE`), scaling
+/// the row count in proportion to how far the averaged frame time is from the target.
+/// Under vsync it ends up oscillating around the largest grid that keeps the refresh rate,
+/// or around the target if that is longer than a refresh.
+struct AdaptiveRows {
+    target_ms: f64,
+    frame: usize,
+    /// Row counts chosen by recent steps.
+    recent: VecDeque<usize>,
+}
+
+impl AdaptiveRows {
+    fn from_env() -> Option<Self> {
+        let target_ms = env_f32("GRID_BENCH_ADAPTIVE", 0.0) as f64;
+        (target_ms > 0.0).then(|| Self {
+            target_ms,
+            frame: 0,
+            recent: VecDeque::new(),
+        })
+    }
+
+    /// The row count for this frame's step, or `None` between steps.
+    fn advance(&mut self, fps: f64, target_hz: f32, rows: usize) -> Option<usize> {
+        self.frame += 1;
+        if self.frame <= RAMP_WARMUP_FRAMES
+            || !self.frame.is_multiple_of(ADAPTIVE_INTERVAL_FRAMES)
+            || fps <= 0.0
+        {
+            return None;
+        }
+
+        let error = if fps >= target_hz as f64 * 0.95 && 1000.0 / fps < self.target_ms {
+            ADAPTIVE_REFRESH_ERROR
+        } else {
+            (self.target_ms * fps / 1000.0 - 1.0).clamp(-ADAPTIVE_MAX_ERROR, ADAPTIVE_MAX_ERROR)
+        };
+        // At least a row either way, so a small grid can still move.
+        let change = (rows as f64 * error * ADAPTIVE_GAIN).round() as isize;
+        let change = if change == 0 {
+            error.signum() as isize
+        } else {
+            change
+        };
+        let rows = rows.saturating_add_signed(change).max(1);
+
+        self.recent.push_back(rows);
+        if self.recent.len() > ADAPTIVE_SETTLE_STEPS {
+            self.recent.pop_front();
+        }
+        Some(rows)
+    }
+
+    /// The average of the recent steps' row counts, once there are enough of them and
+    /// they all lie within `ADAPTIVE_SETTLE_FRACTION` of it.
+    fn settled_rows(&self) -> Option<usize> {
+        if self.recent.len() < ADAPTIVE_SETTLE_STEPS {
+            return None;
+        }
+        let avg = self.recent.iter().sum::<usize>() as f64 / self.recent.len() as f64;
+        self.recent
+            .iter()
+            .all(|&rows| (rows as f64 - avg).abs() <= avg * ADAPTIVE_SETTLE_FRACTION)
+            .then_some(avg.round() as usize)
+    }
+
+    fn status(&self) -> String {
+        match self.settled_rows() {
+            Some(rows) => format!(
+                "Adaptive {:.1} ms: settled at {} rows",
+                self.target_ms, rows
+            ),
+            None => format!("Adaptive {:.1} ms: settling", self.target_ms),
+        }
+    }
+}
+
+/// A cell size change being eased in over `CELL_SIZE_TRANSITION`.
+#[derive(Clone, Copy)]
+struct CellSizeTransition {
+    from: f32,
+    to: f32,
+    started_at: Instant,
+}
+
+impl CellSizeTransition {
+    /// The size for now, and whether the transition is over.
+    fn size(&self) -> (f32, bool) {
+        let elapsed = self.started_at.elapsed().as_secs_f32();
+        let t = (elapsed / CELL_SIZE_TRANSITION.as_secs_f32()).min(1.0);
+        // Smoothstep, easing in and out.
+        let eased = t * t * (3.0 - 2.0 * t);
+        (self.from + (self.to - self.from) * eased, t >= 1.0)
+    }
+}
+
+/// What a cell showed: its hue, whether it was highlighted, its click count, its size
+/// (as bits) and the number in its label.
+type CellState = (u32, bool, Option<u32>, u32, usize);
+
+/// Each cell's state from the previous grid render and how many renders ago it last
+/// changed, for tinting changed cells (`GRID_BENCH_SHOW_MUTATIONS`). The tint blends into
+/// the cell's background so the tree stays the same, but a fading tint still changes
+/// the background, so tinted cells are themselves repainted until it is gone.
+#[derive(Default)]
+struct MutationTracker {
+    cells: HashMap<usize, (CellState, u32)>,
+    /// Some cell is still tinted, so the grid must render again for it to fade.
+    fading: bool,
+}
+
+impl MutationTracker {
+    /// Records the cell's state for this render and returns the tint's opacity: full on
+    /// the render it changed (or first appeared), fading out over `MUTATION_FADE_FRAMES`.
+    fn observe(&mut self, cell_num: usize, state: CellState) -> Option<f32> {
+        let age = match self.cells.get_mut(&cell_num) {
+            Some((prev, age)) if *prev == state => {
+                *age = age.saturating_add(1);
+                *age
+            }
+            Some(entry) => {
+                *entry = (state, 0);
+                0
+            }
+            None => {
+                self.cells.insert(cell_num, (state, 0));
+                0
+            }
+        };
+        let tinted = age < MUTATION_FADE_FRAMES;
+        self.fading |= tinted;
+        tinted.then(|| 1.0 - age as f32 / MUTATION_FADE_FRAMES as f32)
+    }
+}
+
+/// Swings the window size along a sine wave (`W`), so the column count changes every few
+/// frames and each change lays the whole grid out again.
+struct BurstResize {
+    original: gpui::Size<Pixels>,
+    frame: usize,
+}
+
+impl BurstResize {
+    /// The window size for this frame, or `None` once the burst is over and the window
+    /// should go back to `original`.
+    fn advance(&mut self) -> Option<gpui::Size<Pixels>> {
+        self.frame += 1;
+        if self.frame > BURST_RESIZE_FRAMES {
+            return None;
+        }
+        let phase = self.frame as f32 / BURST_RESIZE_PERIOD_FRAMES * std::f32::consts::TAU;
+        let scale = 1.0 + BURST_RESIZE_AMPLITUDE * phase.sin();
+        Some(gpui::size(
+            self.original.width * scale,
+            self.original.height * scale,
+        ))
+    }
+}
+
+/// One step of a `GRID_BENCH_SCRIPT` replay.
+#[derive(Clone, Copy, Debug)]
+enum ScriptAction {
+    AddRow,
+    RemoveRow,
+    /// Set the cell size in pixels.
+    Resize(f32),
+    /// Click the cell with this row-major index.
+    Click(usize),
+    /// Scroll the grid so this many pixels are above the viewport.
+    ScrollTo(f32),
+    ToggleHover,
+    Quit,
+}
+
+impl ScriptAction {
+    fn parse(action: &str, argument: Option<&str>) -> Option<Self> {
+        Some(match action {
+            "add_row" => Self::AddRow,
+            "remove_row" => Self::RemoveRow,
+            "resize" => Self::Resize(argument?.parse().ok()?),
+            "click" => Self::Click(argument?.parse().ok()?),
+            "scroll" => Self::ScrollTo(argument?.parse().ok()?),
+            "toggle_hover" => Self::ToggleHover,
+            "quit" => Self::Quit,
+            _ => return None,
+        })
+    }
+}
+
+/// Replays a script of `<frame> <action> [argument]` lines, counting frames from
+/// startup. Blank lines and `#` comments are skipped.
+struct ScriptPlayer {
+    /// Sorted by frame.
+    actions: Vec<(u64, ScriptAction)>,
+    next: usize,
+    frame: u64,
+}
+
+impl ScriptPlayer {
+    fn from_env() -> Option<Self> {
+        let path = env_var("GRID_BENCH_SCRIPT")?;
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                log::warn!("Failed to read script {path}: {err}");
+                return None;
+            }
+        };
+
+        let mut actions = Vec::new();
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let parsed = words.next().and_then(|frame| {
+                let frame = frame.parse::<u64>().ok()?;
+                let action = ScriptAction::parse(words.next()?, words.next())?;
+                Some((frame, action))
+            });
+            match parsed {
+                Some(action) => actions.push(action),
+                None => {
+                    log::warn!("{path}:{}: can't parse {line:?}", line_number + 1);
+                    return None;
+                }
+            }
+        }
+        actions.sort_by_key(|(frame, _)| *frame);
+
+        Some(Self {
+            actions,
+            next: 0,
+            frame: 0,
+        })
+    }
+
+    /// Advances one frame and returns the actions scheduled up to it.
+    fn advance(&mut self) -> Vec<ScriptAction> {
+        self.frame += 1;
+        let due = self.actions[self.next..]
+            .iter()
+            .take_while(|(frame, _)| *frame <= self.frame)
+            .map(|(_, action)| *action)
+            .collect::<Vec<_>>();
+        self.next += due.len();
+        due
+    }
+
+    fn finished(&self) -> bool {
+        self.next == self.actions.len()
+    }
+}
+
+/// Runs random actions for `GRID_BENCH_SOAK` seconds to exercise state changes a fixed
+/// script never reaches. The actions come from `GRID_BENCH_SEED`, so a crash can be
+/// reproduced by running the same seed again.
+struct SoakTest {
+    state: u64,
+    ends_at: Instant,
+    frame: u64,
+    next_action: u64,
+    actions: u64,
+}
+
+impl SoakTest {
+    fn from_env() -> Option<Self> {
+        let secs = env_usize_opt("GRID_BENCH_SOAK").filter(|&secs| secs > 0)?;
+        let seed = env_var("GRID_BENCH_SEED")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SEED);
+        log::info!("Soak test for {secs}s with seed {seed}");
+
+        Some(Self {
+            state: seed,
+            ends_at: Instant::now() + Duration::from_secs(secs as u64),
+            frame: 0,
+            next_action: 0,
+            actions: 0,
+        })
+    }
+
+    fn random(&mut self, bound: u64) -> u64 {
+        self.state = xorshift64(self.state);
+        self.state % bound.max(1)
+    }
+
+    /// Advances one frame and returns the action due on it, if any.
+    fn advance(&mut self, row_count: usize, col_count: usize, pitch: f32) -> Option<ScriptAction> {
+        self.frame += 1;
+        if self.frame < self.next_action {
+            return None;
+        }
+        self.next_action = self.frame + 1 + self.random(SOAK_MAX_GAP_FRAMES);
+        self.actions += 1;
+
+        let cells = (row_count * col_count) as u64;
+        let height = row_count as f32 * pitch;
+        Some(match self.random(6) {
+            0 if row_count < SOAK_MAX_ROWS => ScriptAction::AddRow,
+            0 | 1 => ScriptAction::RemoveRow,
+            2 => ScriptAction::Resize(8.0 + self.random(121) as f32),
+            3 => ScriptAction::Click(self.random(cells) as usize),
+            4 => ScriptAction::ScrollTo(self.random(height as u64) as f32),
+            _ => ScriptAction::ToggleHover,
+        })
+    }
+
+    fn finished(&self) -> bool {
+        Instant::now() >= self.ends_at
+    }
+}
+
+/// One frame of a `GRID_BENCH_REPLAY` log. Columns the log lacks are `None` and leave
+/// the grid as it is.
+#[derive(Clone, Copy)]
+struct ReplayFrame {
+    rows: Option<usize>,
+    cols: Option<usize>,
+    /// How far the grid was scrolled, in positive pixels.
+    scroll: Option<Point<Pixels>>,
+}
+
+/// Drives the grid's rows, columns and scroll position from a recorded CSV frame log
+/// (`GRID_BENCH_REPLAY`), one logged frame per frame, so the same trajectory can be run
+/// again on another backend. A log that runs out holds its last frame, or starts over
+/// with `GRID_BENCH_REPLAY_LOOP`.
+struct ReplayPlayer {
+    frames: Vec<ReplayFrame>,
+    next: usize,
+    looping: bool,
+}
+
+impl ReplayPlayer {
+    fn from_env() -> Option<Self> {
+        let path = env_var("GRID_BENCH_REPLAY")?;
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                log::warn!("Failed to read replay {path}: {err}");
+                return None;
+            }
+        };
+
+        let mut lines = contents.lines();
+        let header = lines.next().unwrap_or_default();
+        let header = header.split(',').collect::<Vec<_>>();
+        let column = |name| header.iter().position(|&column| column == name);
+        let (rows, cols) = (column("rows"), column("cols"));
+        let (scroll_x, scroll_y) = (column("scroll_x"), column("scroll_y"));
+        if rows.is_none() && cols.is_none() && scroll_x.is_none() && scroll_y.is_none() {
+            log::warn!("{path}: no rows, cols or scroll columns to replay");
+            return None;
+        }
+
+        let frames = lines
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let values = line.split(',').collect::<Vec<_>>();
+                let value = |index: Option<usize>| values.get(index?)?.trim().parse::<f32>().ok();
+                let scroll = match (value(scroll_x), value(scroll_y)) {
+                    (None, None) => None,
+                    (x, y) => Some(gpui::point(px(x.unwrap_or(0.0)), px(y.unwrap_or(0.0)))),
+                };
+                ReplayFrame {
+                    rows: value(rows).map(|rows| rows as usize),
+                    cols: value(cols).map(|cols| cols as usize),
+                    scroll,
+                }
+            })
+            .collect::<Vec<_>>();
+        log::info!("Replaying {} frames from {path}", frames.len());
+
+        Some(Self {
+            frames,
+            next: 0,
+            looping: env_bool("GRID_BENCH_REPLAY_LOOP", false),
+        })
+    }
+
+    /// The next frame to apply, or `None` once a non-looping log has run out.
+    fn advance(&mut self) -> Option<ReplayFrame> {
+        if self.next == self.frames.len() && self.looping {
+            self.next = 0;
+        }
+        let frame = self.frames.get(self.next).copied()?;
+        self.next += 1;
+        Some(frame)
+    }
+}
+
+struct GridBench {
+    fps_view: Entity<FpsView>,
+    focus_handle: FocusHandle,
+    show_overlay: bool,
+    overlay_opacity: f32,
+    /// Where the overlay rests when it isn't being dragged.
+    overlay_corner: Corner,
+    /// The overlay's origin while it is being dragged.
+    overlay_position: Point<Pixels>,
+    /// Pointer offset from the overlay's origin during a drag.
+    overlay_drag: Option<Point<Pixels>>,
+    /// Bounds of the overlay as of the last prepaint.
+    overlay_bounds: Bounds<Pixels>,
+    /// Digits typed into the overlay's row count field while it is being edited.
+    row_input: Option<String>,
+    row_count: usize,
+    /// Keeps the cell count independent of the window width when set.
+    fixed_col_count: Option<usize>,
+    /// The cell width, which the size controls change. Cells are this wide and
+    /// `cell_aspect` times as tall.
+    cell_size: f32,
+    /// `GRID_BENCH_CELL_H` over `GRID_BENCH_CELL_W`, kept as the size changes so both
+    /// dimensions scale together.
+    cell_aspect: f32,
+    /// Ease cell size changes in over `CELL_SIZE_TRANSITION`, laying the grid out again
+    /// every frame of the way, instead of jumping.
+    smooth_cell_size: bool,
+    cell_size_transition: Option<CellSizeTransition>,
+    /// `GRID_BENCH_SHOW_MUTATIONS`: tint the cells that changed since the previous render.
+    mutations: Option<MutationTracker>,
+    /// Space between cells, both within a row and between rows.
+    cell_gap: f32,
+    /// Each row's content and decoration, repeating down the grid: one style for every
+    /// row, or `MIXED_ROWS`.
+    row_styles: Vec<(CellContent, CellDecoration)>,
+    text_color: TextColor,
+    cell_size_mode: CellSizeMode,
+    cell_shape: CellShape,
+    /// Plain divs wrapped around each cell's content.
+    nesting: usize,
+    color_mode: ColorMode,
+    color_space: ColorSpace,
+    hue_basis: HueBasis,
+    pattern: CellPattern,
+    cell_order: CellOrder,
+    layout: GridLayout,
+    update_profile: UpdateProfile,
+    /// `GRID_BENCH_MINIMAL`: every cell a bare square quad with no id, children, listeners
+    /// or styles beyond its size and background, as the floor for per-cell cost.
+    minimal: bool,
+    /// Sticky row and column labels along the top and left edges.
+    show_headers: bool,
+    /// A bar of totals pinned below the grid (`GRID_BENCH_FOOTER`).
+    show_footer: bool,
+    /// Only build the rows that intersect the viewport.
+    virtualize: bool,
+    /// Wrap the grid in a scroll container; without one, overflowing cells are clipped.
+    scrollable: bool,
+    scroll_axis: ScrollAxis,
+    scroll_handle: ScrollHandle,
+    /// Ctrl-scroll distance not yet applied as a cell size step.
+    zoom_remainder: f32,
+    /// Rotate every cell's hue each frame so the whole grid repaints.
+    animate: bool,
+    /// Animate only one color of a checkerboard each frame, alternating between them
+    /// (`GRID_BENCH_ANIMATE=checkerboard`), so half the cells change per frame.
+    checkerboard: bool,
+    anim_phase: u32,
+    enable_hover: bool,
+    hover_mode: HoverMode,
+    /// The cell under the pointer, tracked only when `hover_mode` highlights other cells.
+    hovered: Option<usize>,
+    enable_click: bool,
+    selected: HashSet<usize>,
+    /// Clicks per cell, shown inside the cell once it has any.
+    click_counts: HashMap<usize, u32>,
+    /// The last plainly-clicked cell; shift-click selects the rectangle from here.
+    selection_anchor: Option<usize>,
+    step_size: usize,
+    sweep: Option<SweepRunner>,
+    ramp: Option<StressRamp>,
+    adaptive: Option<AdaptiveRows>,
+    burst_resize: Option<BurstResize>,
+    script: Option<ScriptPlayer>,
+    soak: Option<SoakTest>,
+    replay: Option<ReplayPlayer>,
+    /// Which window this is, for `config.json` and snapshots.
+    window_index: usize,
+    /// The grid's half of a split window, 0 for the left.
+    split_pane: Option<usize>,
+    /// `GRID_BENCH_SNAPSHOT_PATH`; each snapshot adds a timestamp before the extension.
+    snapshot_path: String,
+    started_at: Instant,
+    /// Quit once this much time has passed since `started_at`.
+    duration: Option<Duration>,
+    defaults: GridDefaults,
+}
+
+/// The runtime-adjustable settings as configured at startup, restored by `reset`.
+#[derive(Clone, Copy)]
+struct GridDefaults {
+    row_count: usize,
+    cell_size: f32,
+    cell_gap: f32,
+    step_size: usize,
+    show_overlay: bool,
+    virtualize: bool,
+    animate: bool,
+    enable_hover: bool,
+    enable_click: bool,
+}
+
+impl GridBench {
+    fn new(fps_view: Entity<FpsView>, cx: &mut Context<Self>) -> Self {
+        let sweep = SweepConfig::from_env();
+        let minimal = env_bool("GRID_BENCH_MINIMAL", false);
+        let split_pane = split_cell_sizes().map(|sizes| {
+            let pane = WINDOW_INDEX.get() - 1;
+            (pane, sizes[pane])
+        });
+        // The panes of a split window share a row count, so only the left one steps it.
+        let drives_rows = split_pane.is_none_or(|(pane, _)| pane == 0);
+        let defaults = GridDefaults {
+            row_count: sweep.map_or_else(
+                || env_usize("GRID_BENCH_ROWS", DEFAULT_ROWS),
+                |sweep| sweep.start,
+            ),
+            cell_size: split_pane.map_or_else(
+                || {
+                    env_f32(
+                        "GRID_BENCH_CELL_W",
+                        env_f32("GRID_BENCH_CELL_SIZE", DEFAULT_CELL_SIZE),
+                    )
+                },
+                |(_, cell_size)| cell_size,
+            ),
+            cell_gap: env_f32("GRID_BENCH_GAP", DEFAULT_CELL_GAP).clamp(0.0, MAX_CELL_GAP),
+            step_size: env_usize("GRID_BENCH_STEP", 1),
+            show_overlay: true,
+            virtualize: env_bool("GRID_BENCH_VIRTUALIZE", false),
+            animate: env_bool("GRID_BENCH_ANIMATE", false)
+                || env_var("GRID_BENCH_ANIMATE").as_deref() == Some("checkerboard"),
+            enable_hover: !minimal && env_bool("GRID_BENCH_HOVER", true),
+            enable_click: !minimal && env_bool("GRID_BENCH_CLICK", true),
+        };
+        let ramp = StressRamp::from_env(fps_view.read(cx).target_hz).filter(|_| drives_rows);
+        if let Some(ramp) = &ramp {
+            println!(
+                "Ramp: +{} rows every {} frames until below {:.1} FPS",
+                defaults.step_size, ramp.interval, ramp.min_fps
+            );
+        }
+        Self {
+            fps_view,
+            focus_handle: cx.focus_handle(),
+            show_overlay: defaults.show_overlay,
+            overlay_opacity: OVERLAY_OPACITIES[0],
+            overlay_corner: Corner::TopLeft,
+            overlay_position: Point::default(),
+            overlay_drag: None,
+            overlay_bounds: Bounds::default(),
+            row_input: None,
+            row_count: defaults.row_count,
+            fixed_col_count: env_usize_opt("GRID_BENCH_COLS").filter(|&cols| cols > 0),
+            cell_size: defaults.cell_size,
+            cell_aspect: env_f32("GRID_BENCH_CELL_H", defaults.cell_size).max(1.0)
+                / defaults.cell_size.max(1.0),
+            smooth_cell_size: env_bool("GRID_BENCH_SMOOTH_CELL_SIZE", false),
+            mutations: env_bool("GRID_BENCH_SHOW_MUTATIONS", false).then(MutationTracker::default),
+            cell_size_transition: None,
+            cell_gap: defaults.cell_gap,
+            row_styles: if minimal {
+                vec![(CellContent::Empty, CellDecoration::None)]
+            } else if env_var("GRID_BENCH_CELL_CONTENT").as_deref() == Some("mixed") {
+                MIXED_ROWS.to_vec()
+            } else {
+                vec![(CellContent::from_env(), CellDecoration::from_env())]
+            },
+            text_color: TextColor::from_env(),
+            cell_size_mode: CellSizeMode::from_env(),
+            cell_shape: if minimal {
+                CellShape::Square
+            } else {
+                CellShape::from_env()
+            },
+            nesting: if minimal {
+                0
+            } else {
+                env_usize("GRID_BENCH_NESTING", 0)
+            },
+            color_mode: ColorMode::from_env(),
+            color_space: ColorSpace::from_env(),
+            hue_basis: HueBasis::from_env(),
+            pattern: CellPattern::from_env(),
+            cell_order: CellOrder::from_env(),
+            layout: if minimal {
+                GridLayout::Flat
+            } else {
+                GridLayout::from_env()
+            },
+            update_profile: UpdateProfile::from_env(),
+            minimal,
+            show_headers: env_bool("GRID_BENCH_HEADERS", false),
+            show_footer: env_bool("GRID_BENCH_FOOTER", false),
+            virtualize: defaults.virtualize,
+            scrollable: env_bool("GRID_BENCH_SCROLL", true),
+            scroll_axis: ScrollAxis::from_env(),
+            scroll_handle: ScrollHandle::new(),
+            zoom_remainder: 0.0,
+            animate: defaults.animate,
+            checkerboard: env_var("GRID_BENCH_ANIMATE").as_deref() == Some("checkerboard"),
+            anim_phase: 0,
+            enable_hover: defaults.enable_hover,
+            hover_mode: HoverMode::from_env(),
+            hovered: None,
+            enable_click: defaults.enable_click,
+            selected: HashSet::new(),
+            click_counts: HashMap::new(),
+            selection_anchor: None,
+            step_size: defaults.step_size,
+            sweep: sweep.filter(|_| drives_rows).map(SweepRunner::new),
+            ramp,
+            adaptive: AdaptiveRows::from_env().filter(|_| drives_rows),
+            burst_resize: None,
+            script: ScriptPlayer::from_env(),
+            soak: SoakTest::from_env(),
+            replay: ReplayPlayer::from_env(),
+            window_index: WINDOW_INDEX.get(),
+            split_pane: split_pane.map(|(pane, _)| pane),
+            snapshot_path: output_path("GRID_BENCH_SNAPSHOT_PATH", "snapshot.json"),
+            started_at: Instant::now(),
+            duration: env_usize_opt("GRID_BENCH_DURATION_SECS")
+                .filter(|&secs| secs > 0)
+                .map(|secs| Duration::from_secs(secs as u64)),
+            defaults,
+        }
+    }
+
+    /// The window's settings for `config.json`, as resolved from the environment.
+    fn manifest(&self, width: f32, height: f32, cx: &App) -> serde_json::Value {
+        serde_json::json!({
+            "window": self.window_index,
+            "width": width,
+            "height": height,
+            "rows": self.row_count,
+            "cols": self.fixed_col_count,
+            "cell_size": self.cell_size,
+            "cell_height": self.cell_height(),
+            "cell_gap": self.cell_gap,
+            "step": self.step_size,
+            "nesting": self.nesting,
+            "hover": self.enable_hover,
+            "click": self.enable_click,
+            "virtualize": self.virtualize,
+            "animate": self.animate,
+            "scroll": self.scrollable,
+            "headers": self.show_headers,
+            "footer": self.show_footer,
+            "refresh_hz": self.fps_view.read(cx).target_hz,
+            "duration_secs": self.duration.map(|duration| duration.as_secs()),
+        })
+    }
+
+    /// Writes what the window is showing next to `snapshot_path`. GPUI can't read a
+    /// window's pixels back, so this describes the frame instead of capturing it: the
+    /// settings as `config.json` has them, plus the state at the moment of the snapshot.
+    fn save_snapshot(&self, window: &Window, cx: &App) -> std::io::Result<PathBuf> {
+        let viewport = self.viewport(window);
+        let (width, height) = (f32::from(viewport.width), f32::from(viewport.height));
+        let visible_rows = self.visible_rows(height);
+        let offset = self.scroll_handle.offset();
+        let fps_view = self.fps_view.read(cx);
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let timestamp = utc_timestamp(secs);
+
+        let snapshot = serde_json::json!({
+            "time": timestamp,
+            "elapsed_ms": self.started_at.elapsed().as_millis() as u64,
+            "settings": self.manifest(width, height, cx),
+            "cols": self.calculate_col_count(width),
+            "rows_built": [visible_rows.start, visible_rows.end],
+            "scroll": [-f32::from(offset.x), -f32::from(offset.y)],
+            "cell_size": self.cell_size,
+            "cell_gap": self.cell_gap,
+            "anim_phase": self.anim_phase,
+            "selected": self.selected.len(),
+            "fps": fps_view.render_fps.fps,
+            "frame_ms": fps_view.render_fps.last_frame_ms(),
+        });
+
+        let path = Path::new(&self.snapshot_path);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = path
+            .extension()
+            .map(|extension| format!(".{}", extension.to_string_lossy()))
+            .unwrap_or_default();
+        let mut snapshot_path = path.with_file_name(format!("{stem}_{timestamp}{extension}"));
+        let mut attempt = 1;
+        while snapshot_path.exists() {
+            attempt += 1;
+            snapshot_path = path.with_file_name(format!("{stem}_{timestamp}_{attempt}{extension}"));
+        }
+
+        let json = serde_json::to_string_pretty(&snapshot).map_err(std::io::Error::other)?;
+        std::fs::write(&snapshot_path, json + "\n")?;
+        Ok(snapshot_path)
+    }
+
+    fn schedule_frame_callback(this: Entity<Self>, window: &mut Window) {
+        let this_weak = this.downgrade();
+        window.on_next_frame(move |window, cx| {
+            if let Some(this) = this_weak.upgrade() {
+                this.update(cx, |grid_bench, cx| grid_bench.on_frame(window, cx));
+                Self::schedule_frame_callback(this, window);
+            }
+        });
+    }
+
+    fn on_frame(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let window_width: f32 = self.viewport(window).width.into();
+        let col_count = self.calculate_col_count(window_width);
+
+        if self.animate && !self.fps_view.read(cx).paused {
+            self.anim_phase = (self.anim_phase + 1) % 360;
+            cx.notify();
+        }
+
+        if let Some(transition) = self.cell_size_transition {
+            let (size, finished) = transition.size();
+            self.cell_size = size;
+            if finished {
+                self.cell_size_transition = None;
+            }
+            cx.notify();
+        }
+
+        if let Some(script) = self.script.as_mut() {
+            let actions = script.advance();
+            if script.finished() {
+                log::info!("Script finished at frame {}", script.frame);
+                self.script = None;
+            }
+            for action in actions {
+                log::info!("Script: {action:?}");
+                self.run_script_action(action, col_count, cx);
+            }
+        }
+
+        if let Some(replay) = self.replay.as_mut() {
+            match replay.advance() {
+                Some(frame) => {
+                    if let Some(rows) = frame.rows {
+                        self.row_count = rows.max(1);
+                    }
+                    if let Some(cols) = frame.cols {
+                        self.fixed_col_count = Some(cols.max(1));
+                    }
+                    match frame.scroll {
+                        // Warned about once; the rest of the log's offsets are dropped too.
+                        Some(_) if !self.scrollable => {
+                            log::warn!("Replay: ignoring scroll offsets with GRID_BENCH_SCROLL=0");
+                            for frame in &mut replay.frames {
+                                frame.scroll = None;
+                            }
+                        }
+                        Some(scroll) => {
+                            self.scroll_handle
+                                .set_offset(gpui::point(-scroll.x, -scroll.y));
+                        }
+                        None => {}
+                    }
+                    cx.notify();
+                }
+                None => {
+                    log::info!("Replay finished, holding its last frame");
+                    self.replay = None;
+                }
+            }
+        }
+
+        let row_pitch = self.cell_height() + self.cell_gap;
+        if let Some(soak) = self.soak.as_mut() {
+            if soak.finished() {
+                log::info!(
+                    "Soak test finished after {} frames and {} actions",
+                    soak.frame,
+                    soak.actions
+                );
+                self.soak = None;
+                cx.quit();
+            } else if let Some(action) = soak.advance(self.row_count, col_count, row_pitch) {
+                log::info!("Soak frame {}: {action:?}", soak.frame);
+                self.run_script_action(action, col_count, cx);
+            }
+        }
+
+        if let Some(sweep) = self.sweep.as_mut() {
+            #[cfg(feature = "fiber")]
+            let upload_bytes = window.frame_diagnostics().estimated_instance_upload_bytes as u64;
+            #[cfg(not(feature = "fiber"))]
+            let upload_bytes = 0;
+
+            match sweep.record_frame(col_count, upload_bytes) {
+                Some(rows) if rows != self.row_count => {
+                    self.row_count = rows;
+                    cx.notify();
+                }
+                Some(_) => {}
+                None => {
+                    let path = &sweep.output_path;
+                    match sweep.write_summary(path) {
+                        Ok(()) => log::info!("Wrote sweep summary to {path}"),
+                        Err(err) => log::error!("Failed to write sweep summary to {path}: {err}"),
+                    }
+                    cx.quit();
+                }
+            }
+        }
+
+        if let Some(ramp) = self.ramp.as_mut() {
+            let fps_view = self.fps_view.read(cx);
+            let fps = fps_view.render_fps.fps;
+            let step = if fps_view.paused {
+                Some(false)
+            } else {
+                ramp.advance(fps)
+            };
+            match step {
+                Some(true) => {
+                    self.add_row();
+                    cx.notify();
+                }
+                Some(false) => {}
+                None => {
+                    println!(
+                        "Ramp threshold: {} rows x {} cols ({} cells) at {:.1} FPS",
+                        self.row_count,
+                        col_count,
+                        self.row_count * col_count,
+                        fps
+                    );
+                    self.ramp = None;
+                }
+            }
+        }
+
+        if let Some(adaptive) = self.adaptive.as_mut() {
+            let fps_view = self.fps_view.read(cx);
+            // Frozen counters would hold the FPS still, so the grid would keep moving.
+            if !fps_view.paused
+                && !fps_view.frozen
+                && let Some(rows) =
+                    adaptive.advance(fps_view.render_fps.fps, fps_view.target_hz, self.row_count)
+                && rows != self.row_count
+            {
+                self.row_count = rows;
+                cx.notify();
+            }
+        }
+
+        if let Some(burst_resize) = self.burst_resize.as_mut() {
+            match burst_resize.advance() {
+                Some(size) => window.resize(size),
+                None => {
+                    window.resize(burst_resize.original);
+                    self.burst_resize = None;
+                }
+            }
+        }
+
+        // `FpsView` flushes the frame log and writes its summary from its quit handler.
+        if let Some(duration) = self.duration
+            && self.started_at.elapsed() >= duration
+        {
+            log::info!("Quitting after {}s", duration.as_secs());
+            self.duration = None;
+            cx.quit();
+        }
+    }
+
+    fn run_script_action(
+        &mut self,
+        action: ScriptAction,
+        col_count: usize,
+        cx: &mut Context<Self>,
+    ) {
+        match action {
+            ScriptAction::AddRow => self.add_row(),
+            ScriptAction::RemoveRow => self.remove_row(),
+            ScriptAction::Resize(size) => self.set_cell_size(size),
+            ScriptAction::Click(cell_num) => self.click_cell(cell_num, false, col_count),
+            ScriptAction::ScrollTo(_) if !self.scrollable => {
+                log::warn!("Script: ignoring scroll_to with GRID_BENCH_SCROLL=0");
+            }
+            ScriptAction::ScrollTo(y) => {
+                self.scroll_handle.set_offset(gpui::point(px(0.0), px(-y)));
+            }
+            ScriptAction::ToggleHover => self.toggle_hover(),
+            ScriptAction::Quit => cx.quit(),
+        }
+        self.fps_view.update(cx, |fps_view, _| {
+            fps_view.record_action(format!("{action:?}"));
+        });
+        cx.notify();
+    }
+
+    fn add_row(&mut self) {
+        self.row_count += self.step_size;
+    }
+
+    fn toggle_hover(&mut self) {
+        self.enable_hover = !self.enable_hover;
+        self.hovered = None;
+    }
+
+    fn remove_row(&mut self) {
+        self.row_count = self.row_count.saturating_sub(self.step_size).max(1);
+    }
+
+    fn increase_cell_gap(&mut self) {
+        self.cell_gap = (self.cell_gap + 1.0).min(MAX_CELL_GAP);
+    }
+
+    fn decrease_cell_gap(&mut self) {
+        self.cell_gap = (self.cell_gap - 1.0).max(0.0);
+    }
+
+    /// Moves to the next larger preset; values from `GRID_BENCH_STEP` between presets
+    /// move to the nearest one in that direction.
+    fn increase_step_size(&mut self) {
+        if let Some(step) = STEP_PRESETS.into_iter().find(|&step| step > self.step_size) {
+            self.step_size = step;
+        }
+    }
+
+    fn decrease_step_size(&mut self) {
+        if let Some(step) = STEP_PRESETS
+            .into_iter()
+            .rev()
+            .find(|&step| step < self.step_size)
+        {
+            self.step_size = step;
+        }
+    }
+
+    /// Grows or shrinks the window by whole steps, never below the minimum size.
+    fn resize_window(&self, width_steps: f32, height_steps: f32, window: &mut Window) {
+        let viewport = window.viewport_size();
+        let width = f32::from(viewport.width) + width_steps * WINDOW_SIZE_STEP;
+        let height = f32::from(viewport.height) + height_steps * WINDOW_SIZE_STEP;
+        window.resize(size(
+            px(width.max(MIN_WINDOW_WIDTH)),
+            px(height.max(MIN_WINDOW_HEIGHT)),
+        ));
+    }
+
+    fn increase_cell_size(&mut self) {
+        self.set_cell_size(self.target_cell_size() + 4.0);
+    }
+
+    fn decrease_cell_size(&mut self) {
+        self.set_cell_size(self.target_cell_size() - 4.0);
+    }
+
+    /// The cell size, or the size it is easing towards.
+    fn target_cell_size(&self) -> f32 {
+        self.cell_size_transition
+            .map_or(self.cell_size, |transition| transition.to)
+    }
+
+    fn set_cell_size(&mut self, size: f32) {
+        let size = size.clamp(8.0, 128.0);
+        if self.smooth_cell_size {
+            self.cell_size_transition = Some(CellSizeTransition {
+                from: self.cell_size,
+                to: size,
+                started_at: Instant::now(),
+            });
+        } else {
+            self.cell_size = size;
+        }
+    }
+
+    fn reset(&mut self) {
+        let defaults = self.defaults;
+        self.row_count = defaults.row_count;
+        self.cell_size = defaults.cell_size;
+        self.cell_size_transition = None;
+        self.cell_gap = defaults.cell_gap;
+        self.step_size = defaults.step_size;
+        self.show_overlay = defaults.show_overlay;
+        self.virtualize = defaults.virtualize;
+        self.animate = defaults.animate;
+        self.enable_hover = defaults.enable_hover;
+        self.enable_click = defaults.enable_click;
+        self.zoom_remainder = 0.0;
+        self.click_counts.clear();
+    }
 
-        let _ = file.write_all(b"frame,layout_fibers,paint_fibers,paint_replayed,prepaint_fibers,prepaint_replayed,mutated_segments,total_segments,hitboxes,hitboxes_rebuilt,upload_bytes,quads,mono_sprites,poly_sprites,reconcile_us,intrinsic_sizing_us,layout_us,prepaint_us,paint_us,cleanup_us,total_us\n");
+    /// Goes with `reset`; only the fiber build tracks peaks.
+    #[cfg(feature = "fiber")]
+    fn reset_peaks(&self, cx: &mut Context<Self>) {
+        self.fps_view.update(cx, |fps_view, cx| {
+            fps_view.reset_peaks();
+            cx.notify();
+        });
+    }
 
-        Mutex::new(file)
-    });
+    fn cycle_overlay_opacity(&mut self) {
+        let next = OVERLAY_OPACITIES
+            .iter()
+            .position(|&opacity| opacity == self.overlay_opacity)
+            .map_or(0, |index| (index + 1) % OVERLAY_OPACITIES.len());
+        self.overlay_opacity = OVERLAY_OPACITIES[next];
+    }
 
-    if let Ok(mut file) = log.lock() {
-        let line = format!(
-            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
-            diag.frame_number,
-            diag.layout_fibers,
-            diag.paint_fibers,
-            diag.paint_replayed_subtrees,
-            diag.prepaint_fibers,
-            diag.prepaint_replayed_subtrees,
-            diag.mutated_pool_segments,
-            diag.total_pool_segments,
-            diag.hitboxes_in_snapshot,
-            diag.hitboxes_snapshot_rebuilt,
-            diag.estimated_instance_upload_bytes,
-            diag.quads,
-            diag.monochrome_sprites,
-            diag.polychrome_sprites,
-            diag.reconcile_time.as_micros(),
-            diag.intrinsic_sizing_time.as_micros(),
-            diag.layout_time.as_micros(),
-            diag.prepaint_time.as_micros(),
-            diag.paint_time.as_micros(),
-            diag.cleanup_time.as_micros(),
-            diag.total_time.as_micros(),
-        );
+    fn start_overlay_drag(
+        &mut self,
+        event: &MouseDownEvent,
+        window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) {
+        self.overlay_position = self.overlay_bounds.origin - self.pane_origin(window);
+        self.overlay_drag = Some(event.position - self.overlay_bounds.origin);
+    }
 
-        let _ = file.write_all(line.as_bytes());
+    fn drag_overlay(
+        &mut self,
+        event: &MouseMoveEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(grab_offset) = self.overlay_drag
+            && event.dragging()
+        {
+            self.overlay_position = event.position - grab_offset - self.pane_origin(window);
+            cx.notify();
+        }
     }
-}
 
-fn env_bool(name: &str, default: bool) -> bool {
-    env::var(name)
-        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
-        .unwrap_or(default)
-}
+    /// Snaps the overlay to the window corner nearest its center.
+    fn end_overlay_drag(
+        &mut self,
+        _event: &MouseUpEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.overlay_drag.take().is_none() {
+            return;
+        }
 
-fn env_usize(name: &str, default: usize) -> usize {
-    env::var(name)
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(default)
-}
+        let center = Bounds::new(self.overlay_position, self.overlay_bounds.size).center();
+        let viewport = self.viewport(window);
+        self.overlay_corner = match (
+            center.x < viewport.width / 2.0,
+            center.y < viewport.height / 2.0,
+        ) {
+            (true, true) => Corner::TopLeft,
+            (false, true) => Corner::TopRight,
+            (true, false) => Corner::BottomLeft,
+            (false, false) => Corner::BottomRight,
+        };
+        cx.notify();
+    }
 
-fn env_f32(name: &str, default: f32) -> f32 {
-    env::var(name)
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(default)
-}
+    fn handle_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let keystroke = &event.keystroke;
+        if self.row_input.is_some() {
+            self.edit_row_input(keystroke);
+            cx.notify();
+            return;
+        }
 
-const DEFAULT_ROWS: usize = if cfg!(debug_assertions) { 300 } else { 800 };
-const DEFAULT_CELL_SIZE: f32 = 42.0;
-const DEFAULT_WIDTH: f32 = 960.0;
-const DEFAULT_HEIGHT: f32 = 1080.0;
-const CELL_GAP: f32 = 4.0;
-const GRID_PADDING: f32 = 16.0;
-const FRAME_HISTORY: usize = 60;
+        match keystroke.key.as_str() {
+            "f1" | "`" => self.show_overlay = !self.show_overlay,
+            // Pressing `W` again restarts the burst from the original size.
+            "w" if keystroke.modifiers == gpui::Modifiers::shift() => {
+                self.burst_resize
+                    .get_or_insert(BurstResize {
+                        original: window.viewport_size(),
+                        frame: 0,
+                    })
+                    .frame = 0;
+            }
+            // Capitals, since `c` already toggles the compact HUD.
+            "h" if keystroke.modifiers == gpui::Modifiers::shift() => self.toggle_hover(),
+            "c" if keystroke.modifiers == gpui::Modifiers::shift() => {
+                self.enable_click = !self.enable_click;
+            }
+            // Modified arrows are left free for other shortcuts.
+            _ if keystroke.modifiers.modified() => return,
+            "up" => self.add_row(),
+            "down" => self.remove_row(),
+            "right" => self.increase_cell_size(),
+            "left" => self.decrease_cell_size(),
+            "=" => self.increase_cell_gap(),
+            "-" => self.decrease_cell_gap(),
+            "]" => self.increase_step_size(),
+            "[" => self.decrease_step_size(),
+            "r" => {
+                self.reset();
+                #[cfg(feature = "fiber")]
+                self.reset_peaks(cx);
+            }
+            "g" => self.row_input = Some(String::new()),
+            "space" => self.fps_view.update(cx, |fps_view, cx| {
+                fps_view.toggle_pause();
+                cx.notify();
+            }),
+            "o" => self.cycle_overlay_opacity(),
+            "p" => match self.save_snapshot(window, cx) {
+                Ok(path) => println!("Snapshot: {}", path.display()),
+                Err(err) => log::error!("Failed to write snapshot: {err}"),
+            },
+            "b" => {
+                let rows = (self.row_count, self.row_count.saturating_mul(2));
+                self.row_count = rows.1;
+                self.fps_view.update(cx, |fps_view, _| {
+                    fps_view.batch_append = Some(BatchAppend { rows, before: None });
+                });
+            }
+            "m" => self.fps_view.update(cx, |fps_view, cx| {
+                fps_view.marker_pending = true;
+                cx.notify();
+            }),
+            "c" => self.fps_view.update(cx, |fps_view, cx| {
+                fps_view.compact = !fps_view.compact;
+                cx.notify();
+            }),
+            "f" => self.fps_view.update(cx, |fps_view, cx| {
+                fps_view.toggle_frozen();
+                cx.notify();
+            }),
+            // The counts come from the fiber diagnostics.
+            #[cfg(feature = "fiber")]
+            "d" => self.fps_view.update(cx, |fps_view, cx| {
+                fps_view.count_deltas = !fps_view.count_deltas;
+                cx.notify();
+            }),
+            "i" => self.fps_view.update(cx, |fps_view, cx| {
+                fps_view.instant_fps = !fps_view.instant_fps;
+                cx.notify();
+            }),
+            "x" => self.fps_view.update(cx, |fps_view, cx| {
+                fps_view.precise = !fps_view.precise;
+                cx.notify();
+            }),
+            _ => return,
+        }
+        let key = keystroke.unparse();
+        self.fps_view.update(cx, |fps_view, _| {
+            fps_view.record_action(format!("key {key}"));
+        });
+        cx.notify();
+    }
 
-struct FpsCounter {
-    times: VecDeque<Instant>,
-    fps: f64,
-}
+    /// Handles a key while the row count field is being edited, consuming every key.
+    /// Enter applies the typed count and escape discards it.
+    fn edit_row_input(&mut self, keystroke: &gpui::Keystroke) {
+        let Some(input) = self.row_input.as_mut() else {
+            return;
+        };
 
-impl FpsCounter {
-    fn new() -> Self {
-        Self {
-            times: VecDeque::with_capacity(FRAME_HISTORY + 1),
-            fps: 0.0,
+        match keystroke.key.as_str() {
+            "enter" => {
+                if let Ok(rows) = input.parse::<usize>() {
+                    self.row_count = rows.max(1);
+                }
+                self.row_input = None;
+            }
+            "escape" => self.row_input = None,
+            "backspace" => {
+                input.pop();
+            }
+            key if key.len() == 1 && key.chars().all(|c| c.is_ascii_digit()) => input.push_str(key),
+            _ => {}
         }
     }
 
-    fn record(&mut self) {
-        let now = Instant::now();
-        self.times.push_back(now);
+    fn handle_scroll_wheel(
+        &mut self,
+        event: &ScrollWheelEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if !event.modifiers.control {
+            return;
+        }
+        // Zoom instead of scrolling the grid.
+        cx.stop_propagation();
 
-        if self.times.len() > FRAME_HISTORY {
-            self.times.pop_front();
+        self.zoom_remainder += f32::from(event.delta.pixel_delta(px(ZOOM_STEP_PX)).y);
+        let steps = (self.zoom_remainder / ZOOM_STEP_PX).trunc();
+        self.zoom_remainder -= steps * ZOOM_STEP_PX;
+        if steps == 0.0 {
+            return;
         }
 
-        if self.times.len() >= 2 {
-            if let Some(oldest) = self.times.front() {
-                let elapsed = now.duration_since(*oldest).as_secs_f64();
-                self.fps = (self.times.len() - 1) as f64 / elapsed;
+        for _ in 0..steps.abs() as usize {
+            if steps > 0.0 {
+                self.increase_cell_size();
+            } else {
+                self.decrease_cell_size();
             }
         }
+        cx.notify();
     }
-}
-
-struct FpsView {
-    render_fps: FpsCounter,
-    frame_fps: FpsCounter,
-}
 
-impl FpsView {
-    fn new() -> Self {
-        Self {
-            render_fps: FpsCounter::new(),
-            frame_fps: FpsCounter::new(),
+    fn click_cell(&mut self, cell_num: usize, extend: bool, col_count: usize) {
+        *self.click_counts.entry(cell_num).or_default() += 1;
+        match self.selection_anchor.filter(|_| extend) {
+            Some(anchor) => {
+                let (anchor_row, anchor_col) = (anchor / col_count, anchor % col_count);
+                let (row, col) = (cell_num / col_count, cell_num % col_count);
+                for row in anchor_row.min(row)..=anchor_row.max(row) {
+                    for col in anchor_col.min(col)..=anchor_col.max(col) {
+                        self.selected.insert(row * col_count + col);
+                    }
+                }
+            }
+            None => {
+                if !self.selected.remove(&cell_num) {
+                    self.selected.insert(cell_num);
+                }
+                self.selection_anchor = Some(cell_num);
+            }
         }
     }
 
-    fn schedule_frame_callback(this: Entity<Self>, window: &mut Window) {
-        let this_weak = this.downgrade();
-        window.on_next_frame(move |window, cx| {
-            if let Some(this) = this_weak.upgrade() {
-                this.update(cx, |fps_view, cx| {
-                    fps_view.frame_fps.record();
-                    cx.notify();
-                });
-                Self::schedule_frame_callback(this, window);
-            }
-        });
+    /// Rows to build this frame: the rows intersecting the viewport (plus overscan)
+    /// when virtualizing, otherwise every row.
+    fn visible_rows(&self, viewport_height: f32) -> Range<usize> {
+        if !self.virtualize {
+            return 0..self.row_count;
+        }
+
+        let rows = self.rows_in_view(viewport_height);
+        rows.start.saturating_sub(OVERSCAN_ROWS)..(rows.end + OVERSCAN_ROWS).min(self.row_count)
     }
-}
 
-impl Render for FpsView {
-    fn render(&mut self, window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        window.request_animation_frame();
-        self.render_fps.record();
+    /// Rows intersecting the part of the viewport above the footer.
+    fn rows_in_view(&self, viewport_height: f32) -> Range<usize> {
+        items_in_view(
+            -f32::from(self.scroll_handle.offset().y),
+            viewport_height - self.footer_height(),
+            GRID_PADDING + self.header_size(),
+            self.cell_height() + self.cell_gap,
+            self.row_count,
+        )
+    }
 
-        #[cfg(feature = "fiber")]
-        {
-            let diag = window.frame_diagnostics();
-            log_frame(&diag);
-        }
+    /// Elements the grid builds this frame and the depth of its deepest path. Counts the
+    /// root, scroll container, column, spacers, headers and footer, but not the overlay.
+    fn tree_size(&self, col_count: usize, visible_rows: &Range<usize>) -> (usize, usize) {
+        // Elements in a cell's content and how deep they go.
+        let content_size = |cell_content| match (self.layout, cell_content) {
+            (GridLayout::NestedGrids, _) => (1 + NESTED_GRID_SIZE * (1 + NESTED_GRID_SIZE), 3),
+            (_, CellContent::Empty) => (0, 0),
+            (_, CellContent::LongLabel | CellContent::MonoLabel) => (2, 2),
+            (_, CellContent::Index | CellContent::Image | CellContent::Icon) => (1, 1),
+        };
+        // Content elements across one cell of each built row, and the deepest content.
+        let (content, content_depth) = visible_rows
+            .clone()
+            .map(|row| content_size(self.row_styles[row % self.row_styles.len()].0))
+            .fold((0, 0), |(total, deepest), (content, depth)| {
+                (total + content, deepest.max(depth))
+            });
+        // Absolute cells sit directly in the column, without row elements or spacers.
+        let (rows, spacers) = if self.layout == GridLayout::Absolute {
+            (0, 0)
+        } else {
+            let spacers = usize::from(visible_rows.start > 0)
+                + usize::from(visible_rows.end < self.row_count);
+            (visible_rows.len(), spacers)
+        };
+        // Both strips, their label containers and the wrapper, plus one label per column
+        // and per built row.
+        let headers = if self.show_headers {
+            5 + col_count + visible_rows.len()
+        } else {
+            0
+        };
+        // The footer's four labels, itself and the wrapper around the rest of the grid.
+        let footer = if self.show_footer { 6 } else { 0 };
+        let chrome = 3 + spacers + headers + footer;
 
-        div()
-            .text_color(rgb(0x00ff00))
-            .font_weight(gpui::FontWeight::BOLD)
-            .text_xs()
-            .child(format!("{:.2} FPS", self.render_fps.fps))
+        let element_count = chrome
+            + rows
+            + visible_rows.len() * col_count * (1 + self.nesting)
+            + col_count * content;
+        // root -> (footer wrapper) -> scroll -> column -> row -> cell -> nesting -> content
+        let depth = 4
+            + usize::from(self.show_footer)
+            + usize::from(rows > 0)
+            + self.nesting
+            + content_depth;
+        (element_count, depth)
     }
-}
 
-struct GridBench {
-    fps_view: Entity<FpsView>,
-    row_count: usize,
-    cell_size: f32,
-    enable_hover: bool,
-    enable_click: bool,
-    step_size: usize,
-}
+    /// Space taken by the sticky headers above and to the left of the grid.
+    fn header_size(&self) -> f32 {
+        if self.show_headers { HEADER_SIZE } else { 0.0 }
+    }
 
-impl GridBench {
-    fn new(fps_view: Entity<FpsView>) -> Self {
-        Self {
-            fps_view,
-            row_count: env_usize("GRID_BENCH_ROWS", DEFAULT_ROWS),
-            cell_size: env_f32("GRID_BENCH_CELL_SIZE", DEFAULT_CELL_SIZE),
-            enable_hover: env_bool("GRID_BENCH_HOVER", true),
-            enable_click: env_bool("GRID_BENCH_CLICK", true),
-            step_size: env_usize("GRID_BENCH_STEP", 1),
-        }
+    fn cell_height(&self) -> f32 {
+        self.cell_size * self.cell_aspect
     }
 
-    fn add_row(&mut self) {
-        self.row_count += self.step_size;
+    /// The cell size as the overlay shows it, `W` or `WxH`.
+    fn cell_size_label(&self) -> String {
+        let (width, height) = (self.cell_size as u32, self.cell_height() as u32);
+        if width == height {
+            width.to_string()
+        } else {
+            format!("{width}x{height}")
+        }
     }
 
-    fn remove_row(&mut self) {
-        self.row_count = self.row_count.saturating_sub(self.step_size).max(1);
+    fn footer_height(&self) -> f32 {
+        if self.show_footer { FOOTER_HEIGHT } else { 0.0 }
     }
 
-    fn increase_cell_size(&mut self) {
-        self.cell_size = (self.cell_size + 4.0).min(128.0);
+    /// The window's viewport, or the grid's half of it below the bar in a split window.
+    fn viewport(&self, window: &Window) -> Size<Pixels> {
+        let viewport = window.viewport_size();
+        match self.split_pane {
+            Some(_) => size(viewport.width / 2.0, viewport.height - px(SPLIT_BAR_HEIGHT)),
+            None => viewport,
+        }
     }
 
-    fn decrease_cell_size(&mut self) {
-        self.cell_size = (self.cell_size - 4.0).max(8.0);
+    /// Where the grid's viewport starts in the window, which pointer events are relative to.
+    fn pane_origin(&self, window: &Window) -> Point<Pixels> {
+        match self.split_pane {
+            Some(pane) => gpui::point(
+                window.viewport_size().width / 2.0 * pane as f32,
+                px(SPLIT_BAR_HEIGHT),
+            ),
+            None => Point::default(),
+        }
     }
 
     fn calculate_col_count(&self, window_width: f32) -> usize {
-        let available_width = window_width - (GRID_PADDING * 2.0);
-        let cell_with_gap = self.cell_size + CELL_GAP;
-        ((available_width + CELL_GAP) / cell_with_gap).floor().max(1.0) as usize
+        if let Some(col_count) = self.fixed_col_count {
+            return col_count;
+        }
+
+        let available_width = window_width - (GRID_PADDING * 2.0) - self.header_size();
+        // `cell_size` is at least 8, so this holds even with no gap.
+        let cell_with_gap = self.cell_size + self.cell_gap;
+        ((available_width + self.cell_gap) / cell_with_gap).floor().max(1.0) as usize
     }
 }
 
 impl Render for GridBench {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let window_width: f32 = window.viewport_size().width.into();
-        let col_count = self.calculate_col_count(window_width);
+        let render_started = Instant::now();
+        let viewport = self.viewport(window);
+        let col_count = self.calculate_col_count(viewport.width.into());
         let row_count = self.row_count;
-        let total_cells = row_count * col_count;
+        let hud_hidden = self
+            .fps_view
+            .update(cx, |fps_view, _| fps_view.next_hud_frame());
+        let visible_rows = self.visible_rows(viewport.height.into());
+        // Taken out of `self` while the cells borrow the rest of it.
+        let mutations = self.mutations.take().map(|mut mutations| {
+            mutations.fading = false;
+            RefCell::new(mutations)
+        });
+        let virtualize = self.virtualize;
+        let anim_phase = self.anim_phase;
+        let checkerboard = self.checkerboard;
         let cell_size = self.cell_size;
+        let cell_aspect = self.cell_aspect;
+        let cell_height = self.cell_height();
+        let cell_gap = self.cell_gap;
+        let header_size = self.header_size();
+        let row_styles = &self.row_styles;
+        let text_color = self.text_color;
+        let cell_size_mode = self.cell_size_mode;
+        let cell_shape = self.cell_shape;
+        let nesting = self.nesting;
+        let color_mode = self.color_mode;
+        let color_space = self.color_space;
+        let hue_basis = self.hue_basis;
+        let pattern = self.pattern;
+        let cell_order = self.cell_order;
+        let layout = self.layout;
+        let update_profile = self.update_profile;
+        let minimal = self.minimal;
         let enable_hover = self.enable_hover;
+        let hover_mode = self.hover_mode;
+        let hovered = self.hovered;
         let enable_click = self.enable_click;
+        let selected = &self.selected;
+        let click_counts = &self.click_counts;
+        let grid_bench = cx.entity().downgrade();
 
-        div()
+        // A cell's number, its number in `cell_order`, and its hue this frame.
+        let cell_hue = |row: usize, col: usize| {
+            let cell_num = row * col_count + col;
+            let order_num = cell_order.index(row, col, row_count, col_count);
+            let anim_phase = if checkerboard {
+                checkerboard_phase(row, col, anim_phase)
+            } else {
+                anim_phase
+            };
+            let anim_phase = layout.anim_phase(cell_num, anim_phase);
+            let anim_phase = update_profile.anim_phase(row, anim_phase);
+            let (position, positions) =
+                hue_basis.position(row, col, order_num, row_count, col_count);
+            let hue = pattern.hue(row, col, color_mode.hue(position, positions));
+            (cell_num, order_num, (hue + anim_phase) % 360)
+        };
+        let minimal_cell = |row: usize, col: usize| {
+            let (cell_num, _, hue) = cell_hue(row, col);
+            let cell_size = cell_size_mode.cell_size(cell_size, cell_num);
+            div()
+                .w(px(cell_size))
+                .h(px(cell_size * cell_aspect))
+                .flex_shrink_0()
+                .bg(color_space.color(hue, 70, 60))
+        };
+        let cell = |row: usize, col: usize| {
+            let (cell_content, cell_decoration) = row_styles[row % row_styles.len()];
+            let (cell_num, order_num, hue) = cell_hue(row, col);
+            let color = color_space.color(hue, 70, 60);
+            let hover_color = color_space.color(hue, 80, 80);
+            let cell_size = cell_size_mode.cell_size(cell_size, cell_num);
+            let cell_height = cell_size * cell_aspect;
+            let is_selected = selected.contains(&cell_num);
+            let clicks = click_counts.get(&cell_num).copied();
+            let is_highlighted = enable_hover
+                && hovered
+                    .is_some_and(|hovered| hover_mode.highlights(hovered, cell_num, col_count));
+            let highlighted = is_selected || is_highlighted;
+            let background = if highlighted { hover_color } else { color };
+            let state = (hue, highlighted, clicks, cell_size.to_bits(), order_num);
+            let background = match mutations
+                .as_ref()
+                .and_then(|mutations| mutations.borrow_mut().observe(cell_num, state))
+            {
+                Some(tint) => background.blend(gpui::hsla(MUTATION_TINT_HUE, 1.0, 0.5, tint)),
+                None => background,
+            };
+            let content = match layout {
+                GridLayout::Flat | GridLayout::Absolute => {
+                    cell_content.element(order_num, cell_size, cell_height, text_color)
+                }
+                GridLayout::NestedGrids => {
+                    Some(inner_grid(hue, cell_size.min(cell_height), color_space))
+                }
+            };
+            div()
+                .id(ElementId::NamedInteger("cell".into(), cell_num as u64))
+                .w(px(cell_size))
+                .h(px(cell_height))
+                .flex_shrink_0()
+                .map(|this| cell_shape.apply(this, cell_size.min(cell_height)))
+                .bg(background)
+                .when(cell_decoration.border(), |this| {
+                    this.border_1().border_color(color_space.color(hue, 70, 30))
+                })
+                .when(cell_decoration.shadow(), |this| this.shadow_md())
+                .when(is_selected, |this| {
+                    this.border_2().border_color(rgb(0xffcc00))
+                })
+                .when(enable_hover, |this| {
+                    this.hover(|style| style.bg(hover_color).border_1().border_color(gpui::white()))
+                })
+                .when(enable_hover && hover_mode != HoverMode::Cell, |this| {
+                    let grid_bench = grid_bench.clone();
+                    this.on_hover(move |&is_hovered, _window, cx| {
+                        grid_bench
+                            .update(cx, |this, cx| {
+                                // Leaving one cell and entering the next arrive in either order.
+                                if is_hovered {
+                                    this.hovered = Some(cell_num);
+                                } else if this.hovered == Some(cell_num) {
+                                    this.hovered = None;
+                                } else {
+                                    return;
+                                }
+                                cx.notify();
+                            })
+                            .ok();
+                    })
+                })
+                .flex()
+                .items_center()
+                .justify_center()
+                .text_xs()
+                .text_color(gpui::white())
+                .children(nested(nesting, content))
+                .when_some(clicks, |this, clicks| this.child(format!("×{}", clicks)))
+                .when(enable_click, |this| {
+                    let grid_bench = grid_bench.clone();
+                    this.on_click(move |event, _window, cx| {
+                        log::info!("Clicked cell {}", cell_num);
+                        let extend = event.modifiers().shift;
+                        grid_bench
+                            .update(cx, |this, cx| {
+                                this.click_cell(cell_num, extend, col_count);
+                                cx.notify();
+                            })
+                            .ok();
+                    })
+                })
+        };
+        let cell = &cell;
+        let minimal_cell = &minimal_cell;
+
+        let column = div()
+            // Registered below the scroll container so it runs before the container's own
+            // scroll handling and can stop it. Ctrl-scroll zoom is off without the container.
+            .when(self.scrollable, |this| {
+                this.on_scroll_wheel(cx.listener(Self::handle_scroll_wheel))
+            });
+        let column = if layout == GridLayout::Absolute {
+            let pitch = cell_size + cell_gap;
+            let row_pitch = cell_height + cell_gap;
+            let origin = GRID_PADDING + header_size;
+            let width = origin + col_count as f32 * pitch - cell_gap + GRID_PADDING;
+            let height = origin + row_count as f32 * row_pitch - cell_gap + GRID_PADDING;
+            column
+                .relative()
+                .flex_shrink_0()
+                .w(px(width))
+                .h(px(height))
+                .children(visible_rows.clone().flat_map(move |row| {
+                    (0..col_count).map(move |col| {
+                        cell(row, col)
+                            .absolute()
+                            .left(px(origin + col as f32 * pitch))
+                            .top(px(origin + row as f32 * row_pitch))
+                    })
+                }))
+        } else {
+            column
+                .flex()
+                .flex_col()
+                .p(px(GRID_PADDING))
+                .pt(px(GRID_PADDING + header_size))
+                .pl(px(GRID_PADDING + header_size))
+                .gap(px(cell_gap))
+                .when(visible_rows.start > 0, |this| {
+                    this.child(row_spacer(visible_rows.start, cell_height, cell_gap))
+                })
+                .children(visible_rows.clone().map(move |row| {
+                    let row_div = div()
+                        .flex()
+                        .gap(px(cell_gap))
+                        .when(virtualize, |this| this.h(px(cell_height)));
+                    // Minimal mode forces the flat layout, so only this branch needs it.
+                    if minimal {
+                        row_div.children((0..col_count).map(move |col| minimal_cell(row, col)))
+                    } else {
+                        row_div.children((0..col_count).map(move |col| cell(row, col)))
+                    }
+                }))
+                .when(visible_rows.end < row_count, |this| {
+                    this.child(row_spacer(
+                        row_count - visible_rows.end,
+                        cell_height,
+                        cell_gap,
+                    ))
+                })
+        };
+
+        let grid = div()
+            .id("grid-bench")
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::handle_key_down))
+            // The overlay handles these itself while the pointer is over it.
+            .on_mouse_move(cx.listener(Self::drag_overlay))
+            .on_mouse_up(MouseButton::Left, cx.listener(Self::end_overlay_drag))
             .size_full()
             .bg(rgb(0x1e1e1e))
-            .child(deferred(
-                div()
-                    .absolute()
-                    .top_2()
-                    .left_2()
-                    .px_3()
-                    .py_2()
-                    .bg(gpui::black().opacity(0.7))
-                    .block_mouse_except_scroll()
-                    .rounded_md()
-                    .text_sm()
-                    .flex()
+            .when(self.show_overlay, |this| {
+                if hud_hidden {
+                    // Nothing of the overlay but the FPS view, which still has to log.
+                    let fps_view = div().absolute().size_0().overflow_hidden();
+                    return this.child(fps_view.child(self.fps_view.clone()));
+                }
+                this.child(self.render_overlay(col_count, &visible_rows, viewport, cx))
+            })
+            .map(|this| {
+                if !self.show_footer {
+                    return self.place_grid(this, column, col_count, &visible_rows, cx);
+                }
+                // The footer takes its height from the bottom and the grid fills the rest,
+                // keeping the headers' strips and the scroll container above it.
+                let area = div().relative().flex_1().min_h_0().overflow_hidden();
+                this.flex()
                     .flex_col()
-                    .gap_2()
-                    .child(
-                        div()
-                            .flex()
-                            .flex_col()
-                            .gap_1()
-                            .child(self.fps_view.clone())
-                            .child(
-                                div()
-                                    .text_color(rgb(0xaaaaaa))
-                                    .child(format!(
-                                        "Grid: {}x{} ({} cells) @ {}px",
-                                        row_count, col_count, total_cells, cell_size as u32
-                                    )),
-                            )
-                            .child(
-                                div()
-                                    .text_color(if cfg!(debug_assertions) {
-                                        rgb(0xff8800)
-                                    } else {
-                                        rgb(0x00ff88)
-                                    })
-                                    .child(if cfg!(debug_assertions) {
-                                        "Build: DEBUG"
-                                    } else {
-                                        "Build: RELEASE"
-                                    }),
-                            )
-                            .child(
-                                div()
-                                    .text_color(if cfg!(feature = "fiber") {
-                                        rgb(0xff00ff)
-                                    } else {
-                                        rgb(0x00aaff)
-                                    })
-                                    .child(if cfg!(feature = "fiber") {
-                                        "GPUI: Fiber"
-                                    } else {
-                                        "GPUI: Upstream"
-                                    }),
-                            ),
-                    )
-                    .child(
-                        div()
-                            .flex()
-                            .gap_2()
-                            .child(
-                                div()
-                                    .flex()
-                                    .flex_col()
-                                    .gap_1()
-                                    .child(div().text_color(rgb(0x888888)).child("Rows"))
-                                    .child(
-                                        div()
-                                            .flex()
-                                            .gap_1()
-                                            .child(
-                                                self.control_button(
-                                                    "row-",
-                                                    "-",
-                                                    cx.listener(|this, _, _, cx| {
-                                                        this.remove_row();
-                                                        cx.notify();
-                                                    }),
-                                                ),
-                                            )
-                                            .child(
-                                                self.control_button(
-                                                    "row+",
-                                                    "+",
-                                                    cx.listener(|this, _, _, cx| {
-                                                        this.add_row();
-                                                        cx.notify();
-                                                    }),
-                                                ),
-                                            ),
-                                    ),
-                            )
-                            .child(
-                                div()
-                                    .flex()
-                                    .flex_col()
-                                    .gap_1()
-                                    .child(div().text_color(rgb(0x888888)).child("Cell Size"))
-                                    .child(
-                                        div()
-                                            .flex()
-                                            .gap_1()
-                                            .child(
-                                                self.control_button(
-                                                    "size-",
-                                                    "-",
-                                                    cx.listener(|this, _, _, cx| {
-                                                        this.decrease_cell_size();
-                                                        cx.notify();
-                                                    }),
-                                                ),
-                                            )
-                                            .child(
-                                                self.control_button(
-                                                    "size+",
-                                                    "+",
-                                                    cx.listener(|this, _, _, cx| {
-                                                        this.increase_cell_size();
-                                                        cx.notify();
-                                                    }),
-                                                ),
-                                            ),
-                                    ),
-                            ),
-                    ),
-            ))
-            .child(
+                    .child(self.place_grid(area, column, col_count, &visible_rows, cx))
+                    .child(self.render_footer(col_count, viewport))
+            });
+
+        let render_time = render_started.elapsed();
+        if let Some(mutations) = mutations {
+            let mutations = mutations.into_inner();
+            if mutations.fading {
+                window.request_animation_frame();
+            }
+            self.mutations = Some(mutations);
+        }
+        let offset = self.scroll_handle.offset();
+        let scroll = gpui::point(-offset.x, -offset.y);
+        let grid_size = (row_count, col_count, self.cell_size);
+        let cells_built = visible_rows.len() * col_count;
+        self.fps_view.update(cx, |fps_view, _| {
+            fps_view.grid_render_time = render_time;
+            fps_view.grid_size = grid_size;
+            fps_view.cells_built = cells_built;
+            fps_view.grid_scroll = scroll;
+        });
+        grid
+    }
+}
+
+/// The indices of `count` items laid out every `pitch` from `start` that intersect
+/// `extent` pixels after scrolling by `scroll`.
+fn items_in_view(scroll: f32, extent: f32, start: f32, pitch: f32, count: usize) -> Range<usize> {
+    let first = ((scroll - start) / pitch).floor().max(0.0) as usize;
+    let last = ((scroll + extent - start) / pitch).ceil().max(0.0) as usize;
+    first.min(count)..last.min(count)
+}
+
+/// The phase shown by a cell when only the checkerboard color matching the phase's
+/// parity advances; the other color keeps the previous frame's phase.
+fn checkerboard_phase(row: usize, col: usize, anim_phase: u32) -> u32 {
+    if (row + col + anim_phase as usize).is_multiple_of(2) {
+        anim_phase
+    } else {
+        (anim_phase + 359) % 360
+    }
+}
+
+/// Stands in for `rows` skipped rows, including the gaps that follow them in the column.
+fn row_spacer(rows: usize, cell_height: f32, cell_gap: f32) -> impl IntoElement {
+    div()
+        .flex_shrink_0()
+        .h(px(rows as f32 * (cell_height + cell_gap) - cell_gap))
+}
+
+impl GridBench {
+    /// Adds the grid's column to `parent`, inside the scroll container unless scrolling
+    /// is off, with the headers over it.
+    fn place_grid<E: ParentElement + FluentBuilder>(
+        &self,
+        parent: E,
+        column: gpui::Div,
+        col_count: usize,
+        visible_rows: &Range<usize>,
+        cx: &mut Context<Self>,
+    ) -> E {
+        let parent = if !self.scrollable {
+            parent.child(column)
+        } else {
+            parent.child(
                 div()
                     .size_full()
                     .id("scroll")
-                    .overflow_scroll()
+                    .map(|this| self.scroll_axis.apply(this))
+                    .track_scroll(&self.scroll_handle)
+                    .when(self.virtualize || self.show_headers, |this| {
+                        // The visible range and the header positions depend on the scroll
+                        // offset, so rebuild on scroll.
+                        this.on_scroll_wheel(cx.listener(|_, _, _, cx| cx.notify()))
+                    })
+                    .child(column),
+            )
+        };
+        parent.when(self.show_headers, |this| {
+            this.child(self.render_headers(col_count, visible_rows))
+        })
+    }
+
+    /// Totals for the whole grid, pinned below it whatever the scroll position.
+    fn render_footer(&self, col_count: usize, viewport: Size<Pixels>) -> gpui::Div {
+        let rows = self.rows_in_view(viewport.height.into());
+        let cols = items_in_view(
+            -f32::from(self.scroll_handle.offset().x),
+            viewport.width.into(),
+            GRID_PADDING + self.header_size(),
+            self.cell_size + self.cell_gap,
+            col_count,
+        );
+        let clicks: u32 = self.click_counts.values().sum();
+
+        div()
+            .flex_shrink_0()
+            .h(px(FOOTER_HEIGHT))
+            .px_2()
+            .flex()
+            .items_center()
+            .gap_4()
+            .bg(rgb(0x2a2a2a))
+            .border_t_1()
+            .border_color(rgb(0x444444))
+            .text_xs()
+            .text_color(rgb(0xaaaaaa))
+            .child(format!("{} cells", self.row_count * col_count))
+            .child(format!("{} visible", rows.len() * cols.len()))
+            .child(format!("{} selected", self.selected.len()))
+            .child(format!("{} clicks", clicks))
+    }
+
+    /// "C0, C1, ..." above the grid and "R0, R1, ..." beside it. They sit outside the
+    /// scroll container, following its offset along one axis and staying put along the
+    /// other. The header row also covers the corner above the header column.
+    fn render_headers(&self, col_count: usize, visible_rows: &Range<usize>) -> gpui::Div {
+        let offset = self.scroll_handle.offset();
+        let pitch = self.cell_height() + self.cell_gap;
+        let strip = || {
+            div()
+                .absolute()
+                .top_0()
+                .left_0()
+                .overflow_hidden()
+                .bg(rgb(0x2a2a2a))
+                .text_xs()
+                .text_color(rgb(0xaaaaaa))
+                // Cells scrolled beneath the headers shouldn't react to the pointer.
+                .block_mouse_except_scroll()
+        };
+
+        div()
+            .absolute()
+            .inset_0()
+            .child(
+                strip()
+                    .top(px(HEADER_SIZE))
+                    .bottom_0()
+                    .w(px(HEADER_SIZE))
                     .child(
                         div()
+                            .absolute()
+                            .left_0()
+                            .top(px(GRID_PADDING + visible_rows.start as f32 * pitch) + offset.y)
+                            .w_full()
                             .flex()
                             .flex_col()
-                            .p(px(GRID_PADDING))
-                            .gap(px(CELL_GAP))
-                            .children((0..row_count).map(move |row| {
+                            .gap(px(self.cell_gap))
+                            .children(visible_rows.clone().map(|row| {
                                 div()
+                                    .h(px(self.cell_height()))
+                                    .flex_shrink_0()
                                     .flex()
-                                    .gap(px(CELL_GAP))
-                                    .children((0..col_count).map(move |col| {
-                                        let cell_num = row * col_count + col;
-                                        let hue =
-                                            (cell_num as f32 / total_cells.max(1) as f32 * 360.0) as u32;
-                                        let color = hsv_to_rgb(hue, 70, 60);
-                                        let hover_color = hsv_to_rgb(hue, 80, 80);
-                                        div()
-                                            .id(ElementId::NamedInteger("cell".into(), cell_num as u64))
-                                            .size(px(cell_size))
-                                            .rounded_sm()
-                                            .bg(color)
-                                            .when(enable_hover, |this| {
-                                                this.hover(|style| {
-                                                    style.bg(hover_color).border_1().border_color(gpui::white())
-                                                })
-                                            })
-                                            .flex()
-                                            .items_center()
-                                            .justify_center()
-                                            .text_xs()
-                                            .text_color(gpui::white())
-                                            .child(format!("{}", cell_num))
-                                            .when(enable_click, |this| {
-                                                this.on_click(move |_event, _window, _cx| {
-                                                    log::info!("Clicked cell {}", cell_num);
-                                                })
-                                            })
-                                    }))
+                                    .items_center()
+                                    .justify_center()
+                                    .child(format!("R{}", row))
                             })),
                     ),
             )
+            .child(
+                strip().right_0().h(px(HEADER_SIZE)).child(
+                    div()
+                        .absolute()
+                        .top_0()
+                        .left(px(HEADER_SIZE + GRID_PADDING) + offset.x)
+                        .h_full()
+                        .flex()
+                        .items_center()
+                        .gap(px(self.cell_gap))
+                        .children((0..col_count).map(|col| {
+                            div()
+                                .w(px(self.cell_size))
+                                .flex_shrink_0()
+                                .flex()
+                                .justify_center()
+                                .child(format!("C{}", col))
+                        })),
+                ),
+            )
+    }
+
+    fn render_overlay(
+        &self,
+        col_count: usize,
+        visible_rows: &Range<usize>,
+        viewport: Size<Pixels>,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let row_count = self.row_count;
+        let total_cells = row_count * col_count;
+        let (element_count, tree_depth) = self.tree_size(col_count, visible_rows);
+        let grid_bench = cx.entity().downgrade();
+        // Keeps the overlay clear of the sticky headers along the top and left edges.
+        let header_inset = px(self.header_size() + OVERLAY_INSET);
+        let footer_inset = px(self.footer_height() + OVERLAY_INSET);
+        let viewport_label = if self.split_pane.is_some() {
+            "Pane"
+        } else {
+            "Window"
+        };
+
+        deferred(
+            div()
+                .absolute()
+                .map(|this| match (self.overlay_drag, self.overlay_corner) {
+                    (Some(_), _) => this
+                        .left(self.overlay_position.x)
+                        .top(self.overlay_position.y),
+                    (None, Corner::TopLeft) => this.top(header_inset).left(header_inset),
+                    (None, Corner::TopRight) => this.top(header_inset).right_2(),
+                    (None, Corner::BottomLeft) => this.bottom(footer_inset).left(header_inset),
+                    (None, Corner::BottomRight) => this.bottom(footer_inset).right_2(),
+                })
+                .on_mouse_down(MouseButton::Left, cx.listener(Self::start_overlay_drag))
+                .on_mouse_move(cx.listener(Self::drag_overlay))
+                .on_mouse_up(MouseButton::Left, cx.listener(Self::end_overlay_drag))
+                .px_3()
+                .py_2()
+                .bg(gpui::black().opacity(self.overlay_opacity))
+                .block_mouse_except_scroll()
+                .rounded_md()
+                .text_sm()
+                .flex()
+                .flex_col()
+                .gap_2()
+                .child(
+                    // Records where the overlay landed so a drag can start from there.
+                    canvas(
+                        move |bounds, _, cx| {
+                            grid_bench
+                                .update(cx, |this, _| this.overlay_bounds = bounds)
+                                .ok();
+                        },
+                        |_, _, _, _| {},
+                    )
+                    .absolute()
+                    .inset_0(),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .gap_1()
+                        .child(self.fps_view.clone())
+                        .child(
+                            div()
+                                .text_color(rgb(0xaaaaaa))
+                                .child(format!(
+                                    "Grid: {}x{} ({} cells) @ {}px, gap {}px",
+                                    row_count,
+                                    col_count,
+                                    total_cells,
+                                    self.cell_size_label(),
+                                    self.cell_gap as u32
+                                )),
+                        )
+                        .child(div().text_color(rgb(0xaaaaaa)).child(format!(
+                            "Elements: {} (depth {})",
+                            element_count, tree_depth
+                        )))
+                        .child(div().text_color(rgb(0xaaaaaa)).child(format!(
+                            "Hover: {}, click: {} (H, C)",
+                            if self.enable_hover { "on" } else { "off" },
+                            if self.enable_click { "on" } else { "off" }
+                        )))
+                        .child(div().text_color(rgb(0xaaaaaa)).child(format!(
+                            "{}: {}x{}",
+                            viewport_label,
+                            f32::from(viewport.width) as u32,
+                            f32::from(viewport.height) as u32
+                        )))
+                        .when_some(self.adaptive.as_ref(), |this, adaptive| {
+                            this.child(div().text_color(rgb(0xaaaaaa)).child(adaptive.status()))
+                        })
+                        .child(
+                            div()
+                                .text_color(rgb(0xaaaaaa))
+                                .child(self.color_space.description()),
+                        )
+                        .child(
+                            div()
+                                .text_color(if cfg!(debug_assertions) {
+                                    rgb(0xff8800)
+                                } else {
+                                    rgb(0x00ff88)
+                                })
+                                .child(if cfg!(debug_assertions) {
+                                    "Build: DEBUG"
+                                } else {
+                                    "Build: RELEASE"
+                                }),
+                        )
+                        .child(
+                            div()
+                                .text_color(if cfg!(feature = "fiber") {
+                                    rgb(0xff00ff)
+                                } else {
+                                    rgb(0x00aaff)
+                                })
+                                .child(if cfg!(feature = "fiber") {
+                                    "GPUI: Fiber"
+                                } else {
+                                    "GPUI: Upstream"
+                                }),
+                        ),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .gap_2()
+                        .child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .child(div().text_color(rgb(0x888888)).child("Rows"))
+                                .child(
+                                    div()
+                                        .flex()
+                                        .gap_1()
+                                        .child(
+                                            self.control_button(
+                                                "row-",
+                                                "-",
+                                                cx.listener(|this, _, _, cx| {
+                                                    this.remove_row();
+                                                    cx.notify();
+                                                }),
+                                            ),
+                                        )
+                                        .child(
+                                            self.control_button(
+                                                "row+",
+                                                "+",
+                                                cx.listener(|this, _, _, cx| {
+                                                    this.add_row();
+                                                    cx.notify();
+                                                }),
+                                            ),
+                                        ),
+                                ),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .child(
+                                    div()
+                                        .text_color(rgb(0x888888))
+                                        .child(format!("Step: {}", self.step_size)),
+                                )
+                                .child(
+                                    div()
+                                        .flex()
+                                        .gap_1()
+                                        .child(self.control_button(
+                                            "step-",
+                                            "-",
+                                            cx.listener(|this, _, _, cx| {
+                                                this.decrease_step_size();
+                                                cx.notify();
+                                            }),
+                                        ))
+                                        .child(self.control_button(
+                                            "step+",
+                                            "+",
+                                            cx.listener(|this, _, _, cx| {
+                                                this.increase_step_size();
+                                                cx.notify();
+                                            }),
+                                        )),
+                                ),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .child(div().text_color(rgb(0x888888)).child("Cell Size"))
+                                .child(
+                                    div()
+                                        .flex()
+                                        .gap_1()
+                                        .child(
+                                            self.control_button(
+                                                "size-",
+                                                "-",
+                                                cx.listener(|this, _, _, cx| {
+                                                    this.decrease_cell_size();
+                                                    cx.notify();
+                                                }),
+                                            ),
+                                        )
+                                        .child(
+                                            self.control_button(
+                                                "size+",
+                                                "+",
+                                                cx.listener(|this, _, _, cx| {
+                                                    this.increase_cell_size();
+                                                    cx.notify();
+                                                }),
+                                            ),
+                                        ),
+                                ),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .child(div().text_color(rgb(0x888888)).child("Window"))
+                                .child(
+                                    div()
+                                        .flex()
+                                        .gap_1()
+                                        .child(self.control_button(
+                                            "width-",
+                                            "W-",
+                                            cx.listener(|this, _, window, _| {
+                                                this.resize_window(-1.0, 0.0, window);
+                                            }),
+                                        ))
+                                        .child(self.control_button(
+                                            "width+",
+                                            "W+",
+                                            cx.listener(|this, _, window, _| {
+                                                this.resize_window(1.0, 0.0, window);
+                                            }),
+                                        ))
+                                        .child(self.control_button(
+                                            "height-",
+                                            "H-",
+                                            cx.listener(|this, _, window, _| {
+                                                this.resize_window(0.0, -1.0, window);
+                                            }),
+                                        ))
+                                        .child(self.control_button(
+                                            "height+",
+                                            "H+",
+                                            cx.listener(|this, _, window, _| {
+                                                this.resize_window(0.0, 1.0, window);
+                                            }),
+                                        )),
+                                ),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .child(div().text_color(rgb(0x888888)).child("Defaults"))
+                                .child(self.control_button(
+                                    "reset",
+                                    "Reset",
+                                    cx.listener(|this, _, _, cx| {
+                                        this.reset();
+                                        #[cfg(feature = "fiber")]
+                                        this.reset_peaks(cx);
+                                        cx.notify();
+                                    }),
+                                )),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .child(div().text_color(rgb(0x888888)).child("Go to"))
+                                .child(self.render_row_input(cx)),
+                        ),
+                ),
+        )
+        .with_priority(OVERLAY_PRIORITY)
+    }
+
+    /// Shows the current row count until clicked (or `g` is pressed), then the typed digits.
+    fn render_row_input(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .id("row-input")
+            .w(px(56.0))
+            .px_1()
+            .py_1()
+            .bg(rgb(0x222222))
+            .border_1()
+            .border_color(if self.row_input.is_some() {
+                rgb(0xffcc00)
+            } else {
+                rgb(0x444444)
+            })
+            .rounded_sm()
+            .cursor_text()
+            .map(|this| match &self.row_input {
+                Some(input) => this.text_color(gpui::white()).child(format!("{input}|")),
+                None => this
+                    .text_color(rgb(0x888888))
+                    .child(self.row_count.to_string()),
+            })
+            .on_click(cx.listener(|this, _, window, cx| {
+                this.row_input = Some(String::new());
+                window.focus(&this.focus_handle);
+                cx.notify();
+            }))
     }
-}
 
-impl GridBench {
     fn control_button(
         &self,
         id: &'static str,
@@ -425,6 +5282,125 @@ impl GridBench {
 
 }
 
+/// Two grids side by side in one window (`GRID_BENCH_SPLIT`), differing in cell size but
+/// sharing a row count, for comparing density and cost in a single screenshot. Each pane
+/// has its own FPS view and HUD, but both panes draw in the same frames, so FPS is the
+/// window's and only each grid's render time tells them apart. The bar along the top
+/// shows the window's FPS, then each pane's render time. Keys go to the pane last
+/// clicked, the left one to begin with.
+struct SplitBench {
+    panes: [Entity<GridBench>; 2],
+}
+
+impl SplitBench {
+    fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let panes = [1, 2].map(|index| {
+            // Panes read their `GRID_BENCH_<n>_*` overrides while being built.
+            WINDOW_INDEX.set(index);
+            let fps_view = cx.new(FpsView::new);
+            FpsView::schedule_frame_callback(fps_view.clone(), window);
+            // Keeps the bar's figures current.
+            cx.observe(&fps_view, |_, _, cx| cx.notify()).detach();
+            let grid_bench = cx.new(|cx| GridBench::new(fps_view, cx));
+            GridBench::schedule_frame_callback(grid_bench.clone(), window);
+            grid_bench
+        });
+        WINDOW_INDEX.set(1);
+        window.focus(&panes[0].read(cx).focus_handle);
+
+        // Whichever pane's row count changes, by key, button or ramp, the other follows.
+        for (pane, other) in [(0, 1), (1, 0)] {
+            let other = panes[other].clone();
+            cx.observe(&panes[pane], move |_, pane, cx| {
+                let rows = pane.read(cx).row_count;
+                other.update(cx, |other, cx| {
+                    if other.row_count != rows {
+                        other.row_count = rows;
+                        cx.notify();
+                    }
+                });
+                cx.notify();
+            })
+            .detach();
+        }
+
+        Self { panes }
+    }
+
+    /// Each pane's settings for `config.json`, sized as the panes are.
+    fn manifests(&self, width: f32, height: f32, cx: &App) -> Vec<serde_json::Value> {
+        let (width, height) = (width / 2.0, height - SPLIT_BAR_HEIGHT);
+        self.panes
+            .iter()
+            .map(|pane| pane.read(cx).manifest(width, height, cx))
+            .collect()
+    }
+}
+
+impl Render for SplitBench {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let window_fps = {
+            let fps_view = self.panes[0].read(cx).fps_view.read(cx);
+            format!("window {}", fps_view.fps_text(fps_view.render_fps.fps, 1))
+        };
+        let figures = self.panes.each_ref().map(|pane| {
+            let grid_bench = pane.read(cx);
+            let fps_view = grid_bench.fps_view.read(cx);
+            let render_ms = fps_view.grid_render_time.as_secs_f64() * 1000.0;
+            let text = format!(
+                "{}px: grid {}",
+                grid_bench.cell_size_label(),
+                fps_view.ms_text(render_ms)
+            );
+            let focused = grid_bench.focus_handle.is_focused(window);
+            (text, focused)
+        });
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .bg(rgb(0x1e1e1e))
+            .child(
+                div()
+                    .h(px(SPLIT_BAR_HEIGHT))
+                    .flex_shrink_0()
+                    .flex()
+                    .items_center()
+                    .bg(rgb(0x2a2a2a))
+                    .border_b_1()
+                    .border_color(rgb(0x444444))
+                    .text_xs()
+                    .child(div().px_2().text_color(rgb(0xaaaaaa)).child(window_fps))
+                    .children(figures.into_iter().map(|(text, focused)| {
+                        div()
+                            .flex_1()
+                            .px_2()
+                            .text_color(if focused {
+                                gpui::white()
+                            } else {
+                                rgb(0xaaaaaa).into()
+                            })
+                            .child(text)
+                    })),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_1()
+                    .min_h_0()
+                    .children(self.panes.iter().map(|pane| {
+                        div()
+                            .flex_1()
+                            .min_w_0()
+                            .h_full()
+                            .overflow_hidden()
+                            .child(pane.clone())
+                    })),
+            )
+    }
+}
+
 fn hsv_to_rgb(h: u32, s: u32, v: u32) -> gpui::Hsla {
     gpui::hsla(h as f32 / 360.0, s as f32 / 100.0, v as f32 / 100.0, 1.0)
 }
@@ -434,23 +5410,99 @@ fn main() {
         .filter_level(log::LevelFilter::Info)
         .init();
 
-    let window_width = env_f32("GRID_BENCH_WIDTH", DEFAULT_WIDTH);
-    let window_height = env_f32("GRID_BENCH_HEIGHT", DEFAULT_HEIGHT);
+    match parse_args(env::args().skip(1)) {
+        Ok(settings) if settings.contains_key("GRID_BENCH_HELP") => {
+            print!("{USAGE}");
+            return;
+        }
+        Ok(settings) => {
+            CLI_ARGS.set(settings).ok();
+        }
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    }
 
-    Application::new().run(move |cx: &mut App| {
-        let bounds = Bounds::centered(None, size(px(window_width), px(window_height)), cx);
-        cx.open_window(
-            WindowOptions {
-                window_bounds: Some(WindowBounds::Windowed(bounds)),
-                ..Default::default()
-            },
-            |window, cx| {
-                let fps_view = cx.new(|_| FpsView::new());
-                FpsView::schedule_frame_callback(fps_view.clone(), window);
-                cx.new(|_| GridBench::new(fps_view))
-            },
-        )
-        .unwrap();
-        cx.activate(true);
-    });
+    // A split window stands in for the first two windows, so it's the only one.
+    let split = split_cell_sizes().is_some();
+    let window_count = if split {
+        1
+    } else {
+        env_usize("GRID_BENCH_WINDOWS", 1).max(1)
+    };
+
+    match create_run_dir() {
+        Ok(dir) => {
+            println!("Output directory: {}", dir.display());
+            RUN_DIR.set(dir).ok();
+        }
+        Err(err) => log::error!("Failed to create output directory, writing to ./: {err}"),
+    }
+
+    #[cfg(feature = "fiber")]
+    install_panic_hook();
+
+    LAUNCHED_AT.set(Instant::now()).ok();
+    Application::new()
+        .with_assets(Assets)
+        .run(move |cx: &mut App| {
+            let sizes = (1..=window_count)
+                .map(|index| {
+                    WINDOW_INDEX.set(index);
+                    (
+                        env_f32("GRID_BENCH_WIDTH", DEFAULT_WIDTH),
+                        env_f32("GRID_BENCH_HEIGHT", DEFAULT_HEIGHT),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            // Side by side, centered as a group.
+            let total_width = sizes.iter().map(|(width, _)| width).sum::<f32>();
+            let max_height = sizes.iter().map(|(_, height)| *height).fold(0.0, f32::max);
+            let group = Bounds::centered(None, size(px(total_width), px(max_height)), cx);
+            let mut origin = group.origin;
+            let mut manifests = Vec::with_capacity(window_count);
+
+            for (index, (width, height)) in (1..).zip(sizes) {
+                // Views read their `GRID_BENCH_<n>_*` overrides while being built.
+                WINDOW_INDEX.set(index);
+                let bounds = Bounds::new(origin, size(px(width), px(height)));
+                origin.x += px(width);
+                let options = WindowOptions {
+                    window_bounds: Some(WindowBounds::Windowed(bounds)),
+                    ..Default::default()
+                };
+
+                if split {
+                    cx.open_window(options, |window, cx| {
+                        let split_bench = cx.new(|cx| SplitBench::new(window, cx));
+                        manifests.extend(split_bench.read(cx).manifests(width, height, cx));
+                        split_bench
+                    })
+                    .unwrap();
+                    continue;
+                }
+
+                cx.open_window(options, |window, cx| {
+                    let fps_view = cx.new(FpsView::new);
+                    FpsView::schedule_frame_callback(fps_view.clone(), window);
+                    let grid_bench = cx.new(|cx| GridBench::new(fps_view, cx));
+                    GridBench::schedule_frame_callback(grid_bench.clone(), window);
+                    window.focus(&grid_bench.read(cx).focus_handle);
+                    manifests.push(grid_bench.read(cx).manifest(width, height, cx));
+                    grid_bench
+                })
+                .unwrap();
+            }
+            WINDOW_INDEX.set(1);
+
+            if let Some(dir) = RUN_DIR.get()
+                && let Err(err) = write_manifest(dir, manifests)
+            {
+                log::error!("Failed to write config.json to {}: {err}", dir.display());
+            }
+
+            cx.activate(true);
+        });
 }